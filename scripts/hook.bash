@@ -0,0 +1,44 @@
+
+# Lets krabby run hooks when you `cd` in and out of projects without `kb`.
+__kb_chpwd() {
+  local previous_status=$?
+  if [[ "$PWD" != "$__kb_last_pwd" ]]; then
+    __kb_last_pwd="$PWD"
+    eval "$(krabby chpwd)"
+  fi
+  __kb_report
+  return $previous_status
+}
+
+# Tells about background hook entries that finished, only calling krabby if there is any.
+__kb_report() {
+  local status_file
+  for status_file in "$HOME"/.krabby/logs/*.status; do
+    if [[ -f "$status_file" ]]; then
+      krabby report
+      return
+    fi
+  done
+}
+
+if [[ "$PROMPT_COMMAND" != *__kb_chpwd* ]]; then
+  PROMPT_COMMAND="__kb_chpwd${PROMPT_COMMAND:+;$PROMPT_COMMAND}"
+fi
+
+# Completes project names and the directories inside them, as in `kb api/src/` or `kb api src/`.
+__kb_complete() {
+  local cur="${COMP_WORDS[COMP_CWORD]}" first=1 completion
+  [[ "${COMP_WORDS[1]}" == cd ]] && first=2
+  COMPREPLY=()
+  if (( COMP_CWORD == first )); then
+    while IFS= read -r completion; do
+      COMPREPLY+=("$completion")
+    done < <(krabby complete "$cur")
+  elif (( COMP_CWORD == first + 1 )); then
+    while IFS= read -r completion; do
+      COMPREPLY+=("${completion#*/}")
+    done < <(krabby complete "${COMP_WORDS[first]}/$cur")
+  fi
+}
+
+complete -o nospace -F __kb_complete kb
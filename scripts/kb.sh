@@ -15,19 +15,44 @@ kb() {
     'hook'
     '-h'
     '--help'
-    '-d'
-    '--database'
-    '-f'
-    '--project-file'
     '-V'
     '--version'
   )
 
+  # Options krabby takes before any command, so they apply to jumps too, as in `kb -v api`.
+  local globals=()
+  while [[ $# -gt 0 ]]; do
+    case $1 in
+      '-q' | '--quiet' | '-v' | '--verbose' | --*=* )
+        globals+=("$1")
+        shift
+        ;;
+      '-c' | '--config' | '-d' | '--database' | '-f' | '--project-file' )
+        globals+=("$1" "$2")
+        shift 2
+        ;;
+      * )
+        break
+        ;;
+    esac
+  done
+
+  # Tells whether `$1` is one of the reserved tokens, as a whole word.
+  __kb_reserved() {
+    local token
+    for token in "${RESERVED_TOKENS[@]}"; do
+      if [[ "$token" == "$1" ]]; then
+        return 0
+      fi
+    done
+    return 1
+  }
+
   __kb_cd() {
-    cmd=$(krabby cd $@)
+    cmd=$(krabby "${globals[@]}" cd "$@")
     # We check if the directory exists before we 'cd' into it.
     if [[ "$?" -eq 0 ]]; then
-      eval $cmd
+      eval "$cmd"
     else
       echo $cmd
       return 1
@@ -37,17 +62,17 @@ kb() {
   case $1 in
     # We have to filter out both 'run' and 'cd' commands so we can evaluate.
     'run' | 'r' )
-      cmd="$(krabby run ${@:2})"
-      eval $cmd
+      cmd="$(krabby "${globals[@]}" run "${@:2}")"
+      eval "$cmd"
       ;;
     'cd' )
       __kb_cd "${@:2}"
       ;;
     * )
-      if [[ "${RESERVED_TOKENS[*]}" =~ "$1" ]]; then
-        krabby $@
+      if [[ $# -eq 0 ]] || __kb_reserved "$1"; then
+        krabby "${globals[@]}" "$@"
       else
-        __kb_cd "${@:1}"
+        __kb_cd "$@"
       fi
       ;;
   esac
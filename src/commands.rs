@@ -1,9 +1,15 @@
-use clap::{Args, Subcommand};
+use clap::{Args, Subcommand, ValueEnum};
+use std::path::PathBuf;
 
 #[derive(Debug, Subcommand)]
 pub enum Commands {
     /// Goes to project path
-    Cd { project_name: String },
+    Cd {
+        /// Project to go to, optionally followed by a place or a subdirectory as in `api:web` or `api/src`
+        project_name: String,
+        /// Place or subdirectory of the project to end up in
+        within: Option<String>,
+    },
     /// Manage project hook
     Hook(Hook),
     /// Executes given string
@@ -15,6 +21,21 @@ pub enum Commands {
     Script(Script),
     /// Print the helper script
     Shell(Shell),
+    /// Print what the shell must evaluate after changing directories
+    #[command(hide = true)]
+    Chpwd,
+    /// Print what the shell may complete a project name or subdirectory to
+    #[command(hide = true)]
+    Complete {
+        #[arg(default_value = "")]
+        word: String,
+    },
+    /// Print the outcome of background hook entries that finished
+    #[command(hide = true)]
+    Report,
+    /// Record that the on_first_enter hook of a project succeeded
+    #[command(hide = true)]
+    FirstEntered { project_name: String, hash: String },
 }
 
 #[derive(Debug, Args)]
@@ -31,6 +52,75 @@ pub enum HookCommands {
         #[clap(value_delimiter = ',')]
         hook: Vec<String>,
     },
+    /// Show the hook and the commands it runs
+    Show,
+    /// Run a hook of the project without changing directories
+    Run {
+        #[arg(value_enum, default_value_t = HookKind::Hook)]
+        hook: HookKind,
+    },
+    /// Remove the hook
+    Clear,
+    /// Append a script to the hook, or an inline command with --run
+    Add {
+        entry: String,
+        /// Add the entry as an inline command rather than the name of a script
+        #[arg(long)]
+        run: bool,
+    },
+    /// Remove a script or an inline command from the hook
+    Remove { entry: String },
+    /// Open the project file in your editor and check the hook afterwards
+    Edit,
+    /// Approve the hooks of the project so they can run
+    Allow,
+    /// Revoke the approval of the project hooks
+    Deny,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SortBy {
+    /// The order projects were registered in
+    Registration,
+    Name,
+    /// Most visited and most recently visited first
+    Frecency,
+}
+
+/// Other tools that keep track of directories, to import projects from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ImportSource {
+    Zoxide,
+    Autojump,
+    Ghq,
+    Projectile,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    Toml,
+    Json,
+}
+
+/// What to do with imported projects whose name is taken.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum MergeStrategy {
+    /// Keep the registered project
+    Skip,
+    /// Replace the registered project with the imported one
+    Overwrite,
+    /// Register the imported project under a free name, like `api-2`
+    Rename,
+}
+
+/// The hooks a project can define, named as in `krabby.toml`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+#[value(rename_all = "snake_case")]
+pub enum HookKind {
+    Hook,
+    OnLeave,
+    OnFirstEnter,
+    OnInit,
 }
 
 #[derive(Debug, Args)]
@@ -90,13 +180,103 @@ pub enum ProjectCommands {
     },
     /// Go to project directory
     #[clap(visible_alias = "go")]
-    Cd { project_name: String },
+    Cd {
+        /// Project to go to, optionally followed by a place or a subdirectory as in `api:web` or `api/src`
+        project_name: String,
+        /// Place or subdirectory of the project to end up in
+        within: Option<String>,
+    },
     /// List all registered projects
     #[clap(visible_alias = "ls")]
-    List,
+    List {
+        /// Order to list projects in
+        #[arg(long, value_enum, default_value_t = SortBy::Registration)]
+        sort: SortBy,
+    },
     /// Remove project from Krabby database
     #[clap(visible_alias = "rm")]
     Remove { project_name: String },
+    /// Let a project be reached under another name too
+    Alias {
+        /// The other name
+        alias: String,
+        project_name: String,
+    },
+    /// Remove an alias of a project
+    Unalias { alias: String },
+    /// Give a project another name, keeping everything known about it
+    Rename {
+        project_name: String,
+        new_name: String,
+    },
+    /// Point a project at another directory
+    Move {
+        project_name: String,
+        project_path: String,
+        /// Move the project directory there too
+        #[arg(long)]
+        move_dir: bool,
+    },
+    /// Check every registered project for problems
+    Doctor {
+        /// Offer to fix what can be fixed, as prune does
+        #[arg(long)]
+        fix: bool,
+        /// Fix everything that can be fixed without asking
+        #[arg(short, long, requires = "fix")]
+        yes: bool,
+    },
+    /// Remove or repair registered projects with problems
+    Prune {
+        /// Fix everything that can be fixed without asking
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Find git repositories and krabby projects under a directory and register them
+    Scan {
+        #[arg(default_value = ".")]
+        path: String,
+        /// How many levels below the directory to look
+        #[arg(long, default_value_t = 3)]
+        depth: usize,
+        /// Register everything found without asking
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Print the projects so another machine can import them
+    Export {
+        #[arg(long, value_enum, default_value_t = ExportFormat::Toml)]
+        format: ExportFormat,
+        /// Write paths under `~` or under the directory in an environment variable relative to it
+        #[arg(long, value_name = "~|VAR")]
+        relative_to: Vec<String>,
+    },
+    /// Import the projects of an export, or the directories another tool knows about
+    Import {
+        /// Tool to import from, instead of an export of krabby
+        #[arg(long, value_enum)]
+        from: Option<ImportSource>,
+        /// File to read: an export of krabby or, with --from, one in the format of the tool
+        #[arg(required_unless_present = "from")]
+        file: Option<PathBuf>,
+        /// What to do with imported projects whose name is taken
+        #[arg(long, value_enum, default_value_t = MergeStrategy::Skip, conflicts_with = "from")]
+        strategy: MergeStrategy,
+        /// Leave out directories the tool scored lower than this (zoxide and autojump)
+        #[arg(long, value_name = "N", requires = "from")]
+        min_score: Option<f64>,
+        /// Register everything found without asking
+        #[arg(short, long, requires = "from")]
+        yes: bool,
+    },
+    /// Set a field of a project, like its description, tags or repository
+    Set {
+        project_name: String,
+        /// Field to set: description, tags (comma separated), repository or any other name
+        key: String,
+        /// Leave it out to unset the field
+        value: Option<String>,
+    },
     /// Initialize a krabby project file
     Init {
         /// Specify the name of the project (defaults to directory name)
@@ -0,0 +1,133 @@
+use crate::{project::ProjectName, shell};
+use anyhow::{anyhow, Error};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Settings of the user, shared by every project:
+///
+/// ```toml
+/// verbosity = "quiet"
+///
+/// [hooks]
+/// post_register = "cp ~/templates/.envrc {{project.path}}"
+/// after_enter = "kubectl config use-context {{project.name}}"
+/// ```
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+pub struct Config {
+    #[serde(default)]
+    pub verbosity: Verbosity,
+    /// Hides the output of project hooks unless they fail.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub quiet_hooks: bool,
+    #[serde(default)]
+    pub hooks: UserHooks,
+}
+
+/// How much krabby tells about what it does when entering or leaving a project.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Verbosity {
+    /// Only warnings, like hooks that were not allowed.
+    Quiet,
+    #[default]
+    Normal,
+    /// Also the variables exported and the hooks from the user config.
+    Verbose,
+}
+
+/// Shell commands the user wants to run for every project.
+/// They may refer to `{{project.name}}` and `{{project.path}}`, which are quoted when filled in,
+/// so they must not be inside quotes of their own.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+pub struct UserHooks {
+    /// Runs from the project directory right after a project is registered.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_register: Option<String>,
+    /// Runs in the shell whenever it enters a project, before the project's own hooks.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub before_enter: Option<String>,
+    /// Runs in the shell whenever it enters a project, after the project's own hook.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub after_enter: Option<String>,
+}
+
+impl Config {
+    /// Where the config lives unless told otherwise, usually `~/.config/krabby/config.toml`.
+    pub fn default_path() -> PathBuf {
+        dirs::config_dir()
+            .or_else(dirs::home_dir)
+            .expect("Failed to find the config directory")
+            .join("krabby")
+            .join("config.toml")
+    }
+
+    /// Reads the config at `path`, falling back to the defaults when there is none.
+    pub fn from_file(path: &Path) -> Result<Self, Error> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let contents = std::fs::read_to_string(path)?;
+        toml::from_str(&contents).map_err(|e| {
+            anyhow!(
+                "Failed to read config at {}.\n{}",
+                path.to_string_lossy(),
+                e
+            )
+        })
+    }
+}
+
+/// Fills the placeholders of a user hook with the project it runs for.
+/// The values are quoted, so they are safe to use as they are.
+pub fn expand(template: &str, project_name: &ProjectName, project_path: &Path) -> String {
+    template
+        .replace("{{project.name}}", &shell::quote(&project_name.to_string()))
+        .replace(
+            "{{project.path}}",
+            &shell::quote(&project_path.to_string_lossy()),
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_config_falls_back_to_defaults() {
+        let config = Config::from_file(Path::new("/tmp/no-such-krabby-config.toml")).unwrap();
+        assert_eq!(config, Config::default());
+    }
+
+    #[test]
+    fn user_hooks_are_parsed() {
+        let config: Config = toml::from_str(
+            r#"
+            [hooks]
+            post_register = "echo {{project.name}}"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.hooks.post_register,
+            Some("echo {{project.name}}".to_string())
+        );
+    }
+
+    #[test]
+    fn verbosity_defaults_to_normal() {
+        assert_eq!(Config::default().verbosity, Verbosity::Normal);
+        let config: Config = toml::from_str("verbosity = \"quiet\"").unwrap();
+        assert_eq!(config.verbosity, Verbosity::Quiet);
+        assert!(Verbosity::Quiet < Verbosity::Normal);
+    }
+
+    #[test]
+    fn placeholders_are_expanded_and_quoted() {
+        let expanded = expand(
+            "cd {{project.path}} && echo {{project.name}}",
+            &ProjectName::parse("krabby".into()),
+            Path::new("/tmp/it's here"),
+        );
+        assert_eq!(expanded, "cd '/tmp/it'\\''s here' && echo 'krabby'");
+    }
+}
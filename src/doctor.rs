@@ -0,0 +1,330 @@
+use crate::{
+    database::Database,
+    project::{Project, ProjectName},
+    script::ScriptName,
+};
+use anyhow::{anyhow, Error};
+use indexmap::IndexMap;
+use owo_colors::OwoColorize;
+use std::{
+    fmt::{self, Display},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/// Something wrong with a registered project.
+#[derive(Debug)]
+pub enum Problem {
+    MissingDirectory(PathBuf),
+    NotADirectory(PathBuf),
+    MissingProjectFile,
+    InvalidProjectFile(Error),
+    /// Scripts the hooks of the project run, but its project file does not define.
+    MissingScripts(Vec<ScriptName>),
+    /// The project is in the same directory as this other one, once symlinks are resolved.
+    DuplicatePath(ProjectName),
+    /// The project, or this alias of it, has a `:`, which is read as the start of a place.
+    /// Names could have one before places came along.
+    UnreachableName(ProjectName),
+}
+
+impl Problem {
+    /// Tells whether `fix` knows how to deal with the problem.
+    pub fn is_fixable(&self) -> bool {
+        matches!(
+            self,
+            Self::MissingDirectory(_) | Self::NotADirectory(_) | Self::DuplicatePath(_)
+        )
+    }
+
+    /// What `fix` would do about the problem, phrased as a question for the user.
+    pub fn fix_question(&self, project_name: &ProjectName) -> Option<String> {
+        match self {
+            Self::MissingDirectory(_) | Self::NotADirectory(_) => {
+                Some(format!("Remove {} from the database?", project_name.bold()))
+            }
+            Self::DuplicatePath(original) => Some(format!(
+                "Make {} an alias of {}?",
+                project_name.bold(),
+                original.bold()
+            )),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::MissingDirectory(path) => {
+                write!(
+                    f,
+                    "{} {}.",
+                    path.to_string_lossy().bold(),
+                    "is missing".red().bold()
+                )
+            }
+            Self::NotADirectory(path) => {
+                write!(
+                    f,
+                    "{} {}.",
+                    path.to_string_lossy().bold(),
+                    "is not a directory".red().bold()
+                )
+            }
+            Self::MissingProjectFile => {
+                write!(f, "It has {}.", "no krabby.toml".yellow().bold())
+            }
+            Self::InvalidProjectFile(e) => {
+                write!(
+                    f,
+                    "Its krabby.toml {}:\n{}",
+                    "could not be read".red().bold(),
+                    e
+                )
+            }
+            Self::MissingScripts(scripts) => {
+                let scripts: Vec<String> = scripts.iter().map(ScriptName::to_string).collect();
+                write!(
+                    f,
+                    "Its hooks run {} its krabby.toml {}.",
+                    scripts.join(", ").bold(),
+                    "does not define".red().bold()
+                )
+            }
+            Self::DuplicatePath(original) => {
+                write!(
+                    f,
+                    "It is {} as {}.",
+                    "in the same directory".red().bold(),
+                    original.bold()
+                )
+            }
+            Self::UnreachableName(name) => {
+                write!(
+                    f,
+                    "{} has a ':', so it {}, as krabby reads what follows as a place. Rename it with {}, or drop it with {} if it is an alias.",
+                    name.bold(),
+                    "cannot be reached".red().bold(),
+                    "'kb project rename'".bold(),
+                    "'kb project unalias'".bold()
+                )
+            }
+        }
+    }
+}
+
+/// Goes through every registered project, in order, looking for problems.
+pub fn diagnose(database: &Database) -> Vec<(ProjectName, Problem)> {
+    let mut problems = Vec::new();
+    // Where each directory was first registered, once symlinks are resolved.
+    let mut seen: IndexMap<PathBuf, &ProjectName> = IndexMap::new();
+    for (project_name, entry) in &database.projects {
+        for name in std::iter::once(project_name).chain(&entry.aliases) {
+            if name.to_string().contains(':') {
+                problems.push((project_name.clone(), Problem::UnreachableName(name.clone())));
+            }
+        }
+        if !entry.path.exists() {
+            problems.push((
+                project_name.clone(),
+                Problem::MissingDirectory(entry.path.clone()),
+            ));
+            continue;
+        }
+        if !entry.path.is_dir() {
+            problems.push((
+                project_name.clone(),
+                Problem::NotADirectory(entry.path.clone()),
+            ));
+            continue;
+        }
+        let path = std::fs::canonicalize(&entry.path).unwrap_or_else(|_| entry.path.clone());
+        match seen.get(&path) {
+            Some(original) => problems.push((
+                project_name.clone(),
+                Problem::DuplicatePath((*original).clone()),
+            )),
+            None => {
+                seen.insert(path, project_name);
+            }
+        }
+        if let Some(problem) = check_project_file(&entry.path.join("krabby.toml")) {
+            problems.push((project_name.clone(), problem));
+        }
+    }
+    problems
+}
+
+/// Reads the project file at `path` without giving up at the first hook that does not resolve,
+/// so missing scripts can be told apart from a file that is broken otherwise.
+fn check_project_file(path: &Path) -> Option<Problem> {
+    if !path.is_file() {
+        return Some(Problem::MissingProjectFile);
+    }
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) => return Some(Problem::InvalidProjectFile(e.into())),
+    };
+    let project: Project = match toml::from_str(&contents) {
+        Ok(project) => project,
+        Err(e) => return Some(Problem::InvalidProjectFile(e.into())),
+    };
+    let hooks = [
+        &project.hook,
+        &project.on_leave,
+        &project.on_first_enter,
+        &project.on_init,
+    ]
+    .into_iter()
+    .chain(project.places.values().map(|place| place.hook()));
+    let mut missing: Vec<ScriptName> = Vec::new();
+    for hook in hooks.flatten() {
+        for script_name in hook.script_names() {
+            if !project.scripts.contains_key(script_name) && !missing.contains(script_name) {
+                missing.push(script_name.clone());
+            }
+        }
+    }
+    if !missing.is_empty() {
+        return Some(Problem::MissingScripts(missing));
+    }
+    Project::from_str(&contents)
+        .err()
+        .map(Problem::InvalidProjectFile)
+}
+
+/// Deals with `problem` of `project_name`: projects whose directory is gone are removed,
+/// and projects registered twice become aliases of the first one.
+pub fn fix(
+    database: &mut Database,
+    project_name: &ProjectName,
+    problem: &Problem,
+) -> Result<(), Error> {
+    match problem {
+        Problem::MissingDirectory(_) | Problem::NotADirectory(_) => {
+            database.remove_project(project_name.clone())
+        }
+        Problem::DuplicatePath(original) => {
+            let aliases = database
+                .get_project(project_name)
+                .map(|entry| entry.aliases.clone())
+                .unwrap_or_default();
+            database.remove_project(project_name.clone())?;
+            for alias in std::iter::once(project_name.clone()).chain(aliases) {
+                database.alias_project(original, alias)?;
+            }
+            Ok(())
+        }
+        problem => Err(anyhow!(
+            "There is no fix for this problem, you have to check it yourself:\n{}",
+            problem
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn project_dir(name: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("krabby-doctor-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&path).unwrap();
+        path
+    }
+
+    #[test]
+    fn doctor_finds_every_kind_of_problem() {
+        let healthy = project_dir("healthy");
+        fs::write(healthy.join("krabby.toml"), "name = \"healthy\"\n").unwrap();
+        let bare = project_dir("bare");
+        let broken = project_dir("broken");
+        fs::write(broken.join("krabby.toml"), "name = \n").unwrap();
+        let missing_scripts = project_dir("scripts");
+        fs::write(
+            missing_scripts.join("krabby.toml"),
+            "name = \"scripts\"\nhook = [\"build\"]\non_leave = [\"build\", \"clean\"]\n",
+        )
+        .unwrap();
+        let link = std::env::temp_dir().join(format!("krabby-doctor-link-{}", std::process::id()));
+        let _ = fs::remove_file(&link);
+        std::os::unix::fs::symlink(&healthy, &link).unwrap();
+
+        let mut database = Database::new(None);
+        let name = |name: &str| ProjectName::parse(name.into());
+        // Registered before `:` was forbidden, which the database does not check.
+        let colon = project_dir("colon");
+        fs::write(colon.join("krabby.toml"), "name = \"colon\"\n").unwrap();
+        database.projects.insert(
+            toml::Value::from("old:name").try_into().unwrap(),
+            crate::database::ProjectEntry::new(colon.clone()),
+        );
+        let projects = [
+            ("healthy", healthy.clone()),
+            ("bare", bare.clone()),
+            ("broken", broken.clone()),
+            ("scripts", missing_scripts.clone()),
+            ("gone", PathBuf::from("/no/such/krabby/project")),
+            ("file", healthy.join("krabby.toml")),
+            ("link", link.clone()),
+        ];
+        for (project_name, path) in projects {
+            database
+                .projects
+                .insert(name(project_name), crate::database::ProjectEntry::new(path));
+        }
+
+        let problems = diagnose(&database);
+        let names: Vec<String> = problems
+            .iter()
+            .map(|(project_name, _)| project_name.to_string())
+            .collect();
+        assert_eq!(
+            names,
+            ["old:name", "bare", "broken", "scripts", "gone", "file", "link"]
+        );
+        assert!(matches!(problems[0].1, Problem::UnreachableName(_)));
+        assert!(matches!(problems[1].1, Problem::MissingProjectFile));
+        assert!(matches!(problems[2].1, Problem::InvalidProjectFile(_)));
+        assert!(matches!(&problems[3].1, Problem::MissingScripts(scripts) if scripts.len() == 2));
+        assert!(matches!(problems[4].1, Problem::MissingDirectory(_)));
+        assert!(matches!(problems[5].1, Problem::NotADirectory(_)));
+        assert!(
+            matches!(&problems[6].1, Problem::DuplicatePath(original) if *original == name("healthy"))
+        );
+
+        for dir in [healthy, bare, broken, missing_scripts, colon] {
+            fs::remove_dir_all(dir).unwrap();
+        }
+        fs::remove_file(link).unwrap();
+    }
+
+    #[test]
+    fn fix_removes_missing_projects_and_aliases_duplicates() {
+        let mut database = Database::from_string(
+            r#"
+            [projects]
+            api = "/tmp"
+            gone = "/no/such/krabby/project"
+            backend = "/tmp/../tmp"
+            "#,
+        );
+        let name = |name: &str| ProjectName::parse(name.into());
+        database
+            .alias_project(&name("backend"), name("be"))
+            .unwrap();
+        for (project_name, problem) in diagnose(&database) {
+            if problem.is_fixable() {
+                fix(&mut database, &project_name, &problem).unwrap();
+            }
+        }
+        assert_eq!(database.list_projects(), vec![name("api")]);
+        assert_eq!(
+            database.get_project(&name("api")).unwrap().aliases,
+            vec![name("backend"), name("be")]
+        );
+        assert!(fix(&mut database, &name("api"), &Problem::MissingProjectFile).is_err());
+    }
+}
@@ -0,0 +1,161 @@
+use crate::shell;
+use anyhow::{anyhow, Error};
+use indexmap::IndexMap;
+use owo_colors::OwoColorize;
+use serde::{Deserialize, Serialize};
+
+/// Environment variable holding what the project variables replaced in the shell.
+pub const ENV_BACKUP_VAR: &str = "KRABBY_ENV_BACKUP";
+
+/// Values the shell had before a project exported its `[env]` table.
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+pub struct EnvBackup {
+    /// Variables that did not exist before entering the project.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unset: Vec<String>,
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub previous: IndexMap<String, String>,
+}
+
+impl EnvBackup {
+    /// Reads the backup left in the environment by the project the shell is in, if any.
+    pub fn from_env() -> Option<Self> {
+        std::env::var(ENV_BACKUP_VAR)
+            .ok()
+            .and_then(|backup| toml::from_str(&backup).ok())
+    }
+
+    /// Saves the current value of each of `names`.
+    pub fn capture<'a>(
+        names: impl Iterator<Item = &'a String>,
+        current: impl Fn(&str) -> Option<String>,
+    ) -> Self {
+        let mut backup = Self::default();
+        for name in names {
+            match current(name) {
+                Some(value) => {
+                    backup.previous.insert(name.to_string(), value);
+                }
+                None => backup.unset.push(name.to_string()),
+            }
+        }
+        backup
+    }
+
+    /// The value `name` gets back once the backup is restored,
+    /// or `None` if the backup does not know about it.
+    pub fn lookup(&self, name: &str) -> Option<Option<String>> {
+        if let Some(value) = self.previous.get(name) {
+            return Some(Some(value.to_string()));
+        }
+        if self.unset.iter().any(|unset| unset == name) {
+            return Some(None);
+        }
+        None
+    }
+
+    /// Builds the shell code that puts every variable back the way it was.
+    pub fn restore(&self) -> String {
+        let mut cmd = String::new();
+        for (name, value) in &self.previous {
+            cmd.push_str(&format!("export {}={}\n", name, shell::quote(value)));
+        }
+        for name in &self.unset {
+            cmd.push_str(&format!("unset {}\n", name));
+        }
+        cmd.push_str(&format!("unset {}\n", ENV_BACKUP_VAR));
+        cmd
+    }
+}
+
+/// Builds the shell code that exports `vars`, keeping a backup of what they replace.
+/// `current` tells the value each variable has right before entering the project.
+pub fn activate(
+    vars: &IndexMap<String, String>,
+    current: impl Fn(&str) -> Option<String>,
+) -> String {
+    if vars.is_empty() {
+        return String::new();
+    }
+    let backup = EnvBackup::capture(vars.keys(), current);
+    let mut cmd = format!(
+        "export {}={}\n",
+        ENV_BACKUP_VAR,
+        shell::quote(&toml::to_string(&backup).unwrap())
+    );
+    for (name, value) in vars {
+        cmd.push_str(&format!("export {}={}\n", name, shell::quote(value)));
+    }
+    cmd
+}
+
+/// Makes sure `name` can be used as a shell variable.
+pub fn validate_name(name: &str) -> Result<(), Error> {
+    let mut chars = name.chars();
+    let valid_start = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_');
+    if valid_start && chars.all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return Ok(());
+    }
+    Err(anyhow!("{} is not a valid variable name", name.bold()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(vars: &[(&str, &str)]) -> IndexMap<String, String> {
+        vars.iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn activate_without_variables_does_nothing() {
+        assert!(activate(&IndexMap::new(), |_| None).is_empty());
+    }
+
+    #[test]
+    fn activate_exports_variables_and_backup() {
+        let cmd = activate(&vars(&[("NODE_ENV", "development")]), |_| None);
+        assert_eq!(
+            cmd,
+            "export KRABBY_ENV_BACKUP='unset = [\"NODE_ENV\"]\n'\nexport NODE_ENV='development'\n"
+        );
+    }
+
+    #[test]
+    fn restore_brings_back_previous_values_and_unsets_new_ones() {
+        let backup = EnvBackup::capture(
+            vars(&[("EDITOR", "code"), ("NODE_ENV", "development")]).keys(),
+            |name| (name == "EDITOR").then(|| "vim".to_string()),
+        );
+        assert_eq!(backup.lookup("EDITOR"), Some(Some("vim".into())));
+        assert_eq!(backup.lookup("NODE_ENV"), Some(None));
+        assert_eq!(backup.lookup("HOME"), None);
+        assert_eq!(
+            backup.restore(),
+            "export EDITOR='vim'\nunset NODE_ENV\nunset KRABBY_ENV_BACKUP\n"
+        );
+    }
+
+    #[test]
+    fn backup_survives_the_shell_round_trip() {
+        let backup = EnvBackup::capture(vars(&[("QUOTE", "")]).keys(), |_| {
+            Some("it's \"quoted\"".into())
+        });
+        let serialized = toml::to_string(&backup).unwrap();
+        assert_eq!(toml::from_str::<EnvBackup>(&serialized).unwrap(), backup);
+    }
+
+    #[test]
+    fn variable_names_are_validated() {
+        for name in ["PATH", "_private", "NODE_ENV2"] {
+            assert!(validate_name(name).is_ok(), "{} should be valid", name);
+        }
+        for name in ["", "2FAST", "NODE-ENV", "A B", "$(rm)"] {
+            assert!(validate_name(name).is_err(), "{} should be invalid", name);
+        }
+    }
+}
@@ -1,9 +1,1430 @@
-use crate::script::ScriptName;
-use serde::{Deserialize, Serialize};
+use crate::{
+    database::ACTIVE_PROJECT_VAR,
+    env,
+    script::{Script, ScriptName},
+    shell,
+};
+use anyhow::{anyhow, Error};
+use indexmap::IndexMap;
+use owo_colors::OwoColorize;
+use serde::{Deserialize, Serialize, Serializer};
+use std::{
+    fmt::{self, Display},
+    os::unix::process::CommandExt,
+    path::{Path, PathBuf},
+    process::Stdio,
+    time::{Duration, Instant},
+};
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 #[serde(untagged)]
 pub enum ProjectHook {
     ScriptArray(Vec<ScriptName>),
+    /// A list mixing script names with entry tables, like `["setup", { run = "nvm use" }]`.
+    #[serde(serialize_with = "serialize_entries")]
+    Mixed(Vec<HookEntry>),
+    /// A plain command, handed to the shell as it was written.
+    /// It has no time limit, and its failures are left for the command itself to tell.
     Simple(String),
+    Detailed(HookConfig),
+}
+
+/// Table form of a hook, so it can carry settings along with its entries:
+///
+/// ```toml
+/// [hook]
+/// steps = ["install", { script = "pull", background = true }, { run = "nvm use" }]
+/// on_error = "continue"
+/// timeout = 30
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct HookConfig {
+    #[serde(serialize_with = "serialize_entries")]
+    pub steps: Vec<HookEntry>,
+    #[serde(default)]
+    pub on_error: OnError,
+    /// Seconds each entry may run before krabby kills it and moves on as if it failed.
+    /// Background entries are left alone.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+}
+
+/// A single entry of a hook: a script name, or a table referring to a script
+/// or holding an inline command, along with its settings.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(untagged, deny_unknown_fields)]
+pub enum HookEntry {
+    Script(ScriptName),
+    Detailed {
+        script: ScriptName,
+        /// Detaches the entry from the shell, writing its output to the project log.
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        background: bool,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        when: Option<Condition>,
+    },
+    Inline {
+        run: String,
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        background: bool,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        when: Option<Condition>,
+    },
+}
+
+/// What has to hold for an entry to run, checked by the shell right before it would:
+///
+/// ```toml
+/// hook = [{ script = "install", when = { file_newer = ["package-lock.json", "node_modules"] } }]
+/// ```
+///
+/// Every condition set must hold. Paths are relative to the project directory,
+/// wherever the hook runs from.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct Condition {
+    /// Git branch the project has to be on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+    /// Two paths, where the first has to be newer than the second, or the second missing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_newer: Option<[String; 2]>,
+    /// Environment variable that has to be unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub env_unset: Option<String>,
+}
+
+impl Condition {
+    /// Builds the shell test telling whether every condition holds.
+    /// It runs from the directory in `KRABBY_PROJECT_DIR`, or the current one when it is unset.
+    pub fn test(&self) -> Result<String, Error> {
+        let mut tests = Vec::new();
+        if let Some(branch) = &self.branch {
+            tests.push(format!(
+                "[ \"$(git rev-parse --abbrev-ref HEAD 2>/dev/null)\" = {} ]",
+                shell::quote(branch)
+            ));
+        }
+        if let Some([newer, older]) = &self.file_newer {
+            tests.push(format!(
+                "{{ [ ! -e {older} ] || [ {newer} -nt {older} ]; }}",
+                newer = shell::quote(newer),
+                older = shell::quote(older)
+            ));
+        }
+        if let Some(name) = &self.env_unset {
+            env::validate_name(name)?;
+            tests.push(format!("[ -z \"${{{}+set}}\" ]", name));
+        }
+        match tests.is_empty() {
+            true => Ok("true".to_string()),
+            false => Ok(format!(
+                "( cd \"${{{}:-.}}\" && {} )",
+                ACTIVE_PROJECT_VAR,
+                tests.join(" && ")
+            )),
+        }
+    }
+}
+
+impl Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let value = toml::Value::try_from(self).map_err(|_| fmt::Error)?;
+        let settings: Vec<String> = value
+            .as_table()
+            .into_iter()
+            .flatten()
+            .map(|(key, value)| format!("{} = {}", key, value))
+            .collect();
+        write!(f, "{}", settings.join(", "))
+    }
+}
+
+impl HookEntry {
+    pub fn script_name(&self) -> Option<&ScriptName> {
+        match self {
+            Self::Script(script) | Self::Detailed { script, .. } => Some(script),
+            Self::Inline { .. } => None,
+        }
+    }
+
+    pub fn background(&self) -> bool {
+        match self {
+            Self::Script(_) => false,
+            Self::Detailed { background, .. } | Self::Inline { background, .. } => *background,
+        }
+    }
+
+    pub fn when(&self) -> Option<&Condition> {
+        match self {
+            Self::Script(_) => None,
+            Self::Detailed { when, .. } | Self::Inline { when, .. } => when.as_ref(),
+        }
+    }
+
+    /// The script or command of the entry, as the user wrote it.
+    pub fn describe(&self) -> String {
+        let description = match self {
+            Self::Script(script) | Self::Detailed { script, .. } => script.to_string(),
+            Self::Inline { run, .. } => run.to_string(),
+        };
+        match self.background() {
+            true => format!("{} &", description),
+            false => description,
+        }
+    }
+
+    /// Reads an entry given by the user: an inline command when `inline`, or else a script name,
+    /// which `Project::validate_hook` checks like any other once the entry is in the hook.
+    pub fn from_input(input: &str, inline: bool) -> Self {
+        match inline {
+            true => Self::Inline {
+                run: input.to_string(),
+                background: false,
+                when: None,
+            },
+            false => Self::Script(ScriptName::parse(input.to_string())),
+        }
+    }
+
+    /// Tells whether `input` names this entry, by script name or inline command.
+    pub fn matches(&self, input: &str) -> bool {
+        match self {
+            Self::Script(script) | Self::Detailed { script, .. } => script.to_string() == input,
+            Self::Inline { run, .. } => run == input,
+        }
+    }
+
+    /// The name of the entry and the command it runs, from its script if it has one.
+    fn resolve(&self, scripts: &IndexMap<ScriptName, Script>) -> Result<(String, String), Error> {
+        match (self, self.script_name()) {
+            (Self::Inline { run, .. }, _) => Ok((run.to_string(), run.to_string())),
+            (_, Some(name)) => match scripts.get(name) {
+                Some(script) => Ok((name.to_string(), script.to_string())),
+                None => Err(anyhow!("Hook {} does not match any script", name.bold())),
+            },
+            (_, None) => unreachable!("Only inline entries have no script"),
+        }
+    }
+
+    /// The entry as the user wrote it, along with the command of its script and its settings,
+    /// in a single line. This is what the user approves, so krabby itself has no say in it.
+    pub fn summary(&self, scripts: &IndexMap<ScriptName, Script>) -> Result<String, Error> {
+        let mut summary = match self.resolve(scripts)? {
+            (name, command) if self.script_name().is_some() => format!("{}: {}", name, command),
+            (_, command) => command,
+        };
+        if self.background() {
+            summary.push_str(" &");
+        }
+        if let Some(condition) = self.when() {
+            condition.test()?;
+            summary.push_str(&format!(" (when {})", condition));
+        }
+        Ok(summary)
+    }
+
+    /// Resolves the entry into the command it runs,
+    /// guarded by its conditions so it is skipped when they do not hold.
+    pub fn step(&self, scripts: &IndexMap<ScriptName, Script>) -> Result<HookStep, Error> {
+        let (name, command) = self.resolve(scripts)?;
+        let command = match self.when() {
+            Some(condition) => format!(
+                "if {}; then\n{}\nelse echo \"Skipping hook entry\" {} \"as its conditions do not hold.\"; fi",
+                condition.test()?,
+                command,
+                shell::quote(&name)
+            ),
+            None => command,
+        };
+        Ok(HookStep {
+            name,
+            command,
+            background: self.background(),
+        })
+    }
+}
+
+/// toml cannot write plain values and tables in the same array,
+/// so script names are written as tables whenever any other entry is one.
+fn serialize_entries<S: Serializer>(
+    entries: &[HookEntry],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    if entries
+        .iter()
+        .all(|entry| matches!(entry, HookEntry::Script(_)))
+    {
+        return entries.serialize(serializer);
+    }
+    serializer.collect_seq(entries.iter().map(|entry| match entry {
+        HookEntry::Script(script) => HookEntry::Detailed {
+            script: script.clone(),
+            background: false,
+            when: None,
+        },
+        entry => entry.clone(),
+    }))
+}
+
+/// What to do with the remaining entries when one of them fails.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OnError {
+    #[default]
+    Stop,
+    Continue,
+}
+
+/// A single resolved entry of a hook.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HookStep {
+    pub name: String,
+    pub command: String,
+    pub background: bool,
+}
+
+/// Where the background entries of a project hook write their output and exit codes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HookLog {
+    pub output: PathBuf,
+    pub status: PathBuf,
+}
+
+impl HookLog {
+    pub fn new(project_name: &str) -> Self {
+        Self::in_dir(&log_dir(), project_name)
+    }
+
+    pub fn in_dir(dir: &Path, project_name: &str) -> Self {
+        Self {
+            output: dir.join(format!("{}.log", project_name)),
+            status: dir.join(format!("{}.status", project_name)),
+        }
+    }
+}
+
+/// Directory holding the logs of background hook entries.
+pub fn log_dir() -> PathBuf {
+    dirs::home_dir()
+        .expect("Failed to find home directory")
+        .join(".krabby")
+        .join("logs")
+}
+
+/// Outcome of a background entry, written to the project status file once it is over.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackgroundReport {
+    pub project: String,
+    pub entry: String,
+    pub code: String,
+    pub log: PathBuf,
+}
+
+impl BackgroundReport {
+    pub fn success(&self) -> bool {
+        self.code == "0"
+    }
+}
+
+impl ProjectHook {
+    pub fn on_error(&self) -> OnError {
+        match self {
+            Self::Detailed(config) => config.on_error,
+            Self::ScriptArray(_) | Self::Mixed(_) | Self::Simple(_) => OnError::default(),
+        }
+    }
+
+    pub fn timeout(&self) -> Option<u64> {
+        match self {
+            Self::Detailed(config) => config.timeout,
+            Self::ScriptArray(_) | Self::Mixed(_) | Self::Simple(_) => None,
+        }
+    }
+
+    /// The entries of the hook as the user wrote them, without what krabby wraps them in.
+    pub fn describe(&self) -> String {
+        match self {
+            Self::Simple(cmd) => cmd.to_string(),
+            Self::ScriptArray(names) => names
+                .iter()
+                .map(ScriptName::to_string)
+                .collect::<Vec<String>>()
+                .join(", "),
+            Self::Mixed(entries) | Self::Detailed(HookConfig { steps: entries, .. }) => entries
+                .iter()
+                .map(HookEntry::describe)
+                .collect::<Vec<String>>()
+                .join(", "),
+        }
+    }
+
+    /// The hook as the user wrote it, one line per entry followed by the settings it has.
+    pub fn summary(&self, scripts: &IndexMap<ScriptName, Script>) -> Result<Vec<String>, Error> {
+        let mut lines = match self {
+            Self::Simple(cmd) => vec![cmd.to_string()],
+            Self::ScriptArray(names) => names
+                .iter()
+                .map(|name| HookEntry::Script(name.clone()).summary(scripts))
+                .collect::<Result<_, _>>()?,
+            Self::Mixed(entries) | Self::Detailed(HookConfig { steps: entries, .. }) => entries
+                .iter()
+                .map(|entry| entry.summary(scripts))
+                .collect::<Result<_, _>>()?,
+        };
+        if self.on_error() == OnError::Continue {
+            lines.push("on_error = \"continue\"".to_string());
+        }
+        if let Some(seconds) = self.timeout() {
+            lines.push(format!("timeout = {}", seconds));
+        }
+        Ok(lines)
+    }
+
+    /// Names of the scripts the hook refers to.
+    pub fn script_names(&self) -> Vec<&ScriptName> {
+        match self {
+            Self::Simple(_) => Vec::new(),
+            Self::ScriptArray(names) => names.iter().collect(),
+            Self::Mixed(entries) | Self::Detailed(HookConfig { steps: entries, .. }) => {
+                entries.iter().filter_map(HookEntry::script_name).collect()
+            }
+        }
+    }
+
+    /// Builds a hook made of `entry` alone, in its simplest form.
+    pub fn from_entry(entry: HookEntry) -> Self {
+        match entry {
+            HookEntry::Script(name) => Self::ScriptArray(vec![name]),
+            HookEntry::Inline {
+                run,
+                background: false,
+                when: None,
+            } => Self::Simple(run),
+            entry => Self::Mixed(vec![entry]),
+        }
+    }
+
+    /// Appends `entry` to the hook, keeping its form whenever it can.
+    pub fn add_entry(self, entry: HookEntry) -> Self {
+        match (self, entry) {
+            (Self::ScriptArray(mut names), HookEntry::Script(name)) => {
+                names.push(name);
+                Self::ScriptArray(names)
+            }
+            (Self::ScriptArray(names), entry) => Self::Mixed(
+                names
+                    .into_iter()
+                    .map(HookEntry::Script)
+                    .chain(std::iter::once(entry))
+                    .collect(),
+            ),
+            (Self::Simple(run), entry) => Self::Mixed(vec![
+                HookEntry::Inline {
+                    run,
+                    background: false,
+                    when: None,
+                },
+                entry,
+            ]),
+            (Self::Mixed(mut entries), entry) => {
+                entries.push(entry);
+                Self::Mixed(entries)
+            }
+            (Self::Detailed(mut config), entry) => {
+                config.steps.push(entry);
+                Self::Detailed(config)
+            }
+        }
+    }
+
+    /// Removes the entries `input` names, by script name or inline command.
+    /// Returns `None` when there is nothing left of the hook.
+    pub fn remove_entry(self, input: &str) -> Result<Option<Self>, Error> {
+        let not_found = || anyhow!("{} is not an entry of the hook.", input.bold());
+        let keep = |entries: Vec<HookEntry>| -> Result<Vec<HookEntry>, Error> {
+            let len = entries.len();
+            let kept: Vec<HookEntry> = entries
+                .into_iter()
+                .filter(|entry| !entry.matches(input))
+                .collect();
+            match kept.len() == len {
+                true => Err(not_found()),
+                false => Ok(kept),
+            }
+        };
+        let hook = match self {
+            Self::Simple(run) if run == input => return Ok(None),
+            Self::Simple(_) => return Err(not_found()),
+            Self::ScriptArray(names) => {
+                let len = names.len();
+                let kept: Vec<ScriptName> = names
+                    .into_iter()
+                    .filter(|name| name.to_string() != input)
+                    .collect();
+                if kept.len() == len {
+                    return Err(not_found());
+                }
+                Self::ScriptArray(kept)
+            }
+            Self::Mixed(entries) => Self::Mixed(keep(entries)?),
+            Self::Detailed(mut config) => {
+                config.steps = keep(config.steps)?;
+                Self::Detailed(config)
+            }
+        };
+        match hook {
+            Self::ScriptArray(ref names) if names.is_empty() => Ok(None),
+            Self::Mixed(ref entries) if entries.is_empty() => Ok(None),
+            Self::Detailed(ref config) if config.steps.is_empty() => Ok(None),
+            hook => Ok(Some(hook)),
+        }
+    }
+
+    /// Resolves every entry of the hook into the command it runs.
+    pub fn steps(&self, scripts: &IndexMap<ScriptName, Script>) -> Result<Vec<HookStep>, Error> {
+        match self {
+            Self::Simple(cmd) => Ok(vec![HookStep {
+                name: cmd.to_string(),
+                command: cmd.to_string(),
+                background: false,
+            }]),
+            Self::ScriptArray(names) => names
+                .iter()
+                .map(|name| HookEntry::Script(name.clone()).step(scripts))
+                .collect(),
+            Self::Mixed(entries) | Self::Detailed(HookConfig { steps: entries, .. }) => {
+                entries.iter().map(|entry| entry.step(scripts)).collect()
+            }
+        }
+    }
+
+    /// Builds the shell code to be evaluated by the `kb` function.
+    pub fn render(
+        &self,
+        scripts: &IndexMap<ScriptName, Script>,
+        log: &HookLog,
+    ) -> Result<String, Error> {
+        let steps = self.steps(scripts)?;
+        match self {
+            // A plain command is handed to the shell as it was written.
+            Self::Simple(cmd) => Ok(cmd.to_string()),
+            _ => Ok(render(&steps, self.on_error(), self.timeout(), log)),
+        }
+    }
+
+    /// Runs the hook from krabby itself, entry by entry, inside `dir`.
+    pub fn execute(
+        &self,
+        scripts: &IndexMap<ScriptName, Script>,
+        log: &HookLog,
+        dir: &Path,
+    ) -> Result<(), Error> {
+        execute(
+            &self.steps(scripts)?,
+            self.on_error(),
+            self.timeout(),
+            log,
+            dir,
+        )
+    }
+}
+
+/// Shell code running `step` on its own, appending its output to the log
+/// and its exit code to the status file once it is done.
+fn background(step: &HookStep, log: &HookLog) -> String {
+    let log_dir = log.output.parent().unwrap_or(Path::new("."));
+    format!(
+        "mkdir -p {} && sh -c {} >> {} 2>&1 < /dev/null; printf '%s %s\\n' \"$?\" {} >> {}",
+        shell::quote(&log_dir.to_string_lossy()),
+        shell::quote(&step.command),
+        shell::quote(&log.output.to_string_lossy()),
+        shell::quote(&step.name),
+        shell::quote(&log.status.to_string_lossy())
+    )
+}
+
+/// Exit code of `timeout` when the command ran out of time.
+const TIMED_OUT: i32 = 124;
+
+/// Finds `timeout`, or `gtimeout` as GNU coreutils are called on macOS,
+/// warning that entries run without a time limit when there is neither.
+const FIND_TIMEOUT: &str = "__kb_timeout=$(command -v timeout || command -v gtimeout)
+[ -n \"$__kb_timeout\" ] || echo \"Neither timeout nor gtimeout was found, so hook entries run without a time limit.\"
+";
+
+pub fn render(
+    steps: &[HookStep],
+    on_error: OnError,
+    timeout: Option<u64>,
+    log: &HookLog,
+) -> String {
+    let command = |step: &HookStep| {
+        match (step.background, timeout) {
+        // Starting it from a subshell keeps it out of the job list of the interactive shell.
+        (true, _) => format!("( ( {} ) > /dev/null 2>&1 & )", background(step, log)),
+        (false, Some(seconds)) => format!(
+            "if [ -n \"$__kb_timeout\" ]; then \"$__kb_timeout\" {seconds} sh -c {command}; else sh -c {command}; fi",
+            seconds = seconds,
+            command = shell::quote(&step.command)
+        ),
+        (false, None) => step.command.to_string(),
+    }
+    };
+    // Tells the user which entry failed and how, from the exit code it left in `$?`,
+    // and keeps that code so the hook as a whole fails too.
+    let report = |step: &HookStep, outcome: &str| {
+        let failed = format!(
+            "echo \"Hook entry\" {} \"failed with exit code $__kb_code{}\"",
+            shell::quote(&step.name),
+            outcome
+        );
+        let reported = match timeout {
+            Some(seconds) if !step.background => format!(
+                "__kb_code=$?; if [ \"$__kb_code\" -eq {} ]; then echo \"Hook entry\" {} \"timed out after {} seconds and was killed{}\"; else {}; fi",
+                TIMED_OUT,
+                shell::quote(&step.name),
+                seconds,
+                outcome,
+                failed
+            ),
+            _ => format!("__kb_code=$?; {}", failed),
+        };
+        format!("{}; __kb_status=$__kb_code", reported)
+    };
+    let body = match on_error {
+        // The single iteration loop gives us a `break` that works the same in bash and zsh.
+        OnError::Stop => {
+            let mut lines = vec!["for __kb_hook in 1; do".to_string()];
+            for step in steps {
+                lines.push(format!(
+                    "{{ {}\n}} || {{ {}; break; }}",
+                    command(step),
+                    report(step, ".")
+                ));
+            }
+            lines.push("done".to_string());
+            lines.join("\n")
+        }
+        OnError::Continue => steps
+            .iter()
+            .map(|step| {
+                format!(
+                    "{{ {}\n}} || {{ {}; }}",
+                    command(step),
+                    report(step, ", continuing.")
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("\n"),
+    };
+    let find_timeout = match timeout.is_some() && steps.iter().any(|step| !step.background) {
+        true => FIND_TIMEOUT,
+        false => "",
+    };
+    format!(
+        "{}__kb_status=0\n{}\n{}",
+        find_timeout,
+        body,
+        clean_up(
+            "__kb_status",
+            &["__kb_status", "__kb_code", "__kb_hook", "__kb_timeout"]
+        )
+    )
+}
+
+/// Shell code unsetting `variables`, which the generated code leaves in the user's shell,
+/// and failing if `status`, one of them, was not 0.
+/// `eval` reads the status before `unset` runs, as `return` would leave the `kb` function itself.
+fn clean_up(status: &str, variables: &[&str]) -> String {
+    format!(
+        "eval \"unset {}; [ ${} -eq 0 ]\"",
+        variables.join(" "),
+        status
+    )
+}
+
+/// Wraps the shell code of a hook so its output only shows up when it fails.
+/// It still runs in the current shell, so it can change its variables.
+pub fn silence(cmd: &str) -> String {
+    format!(
+        "__kb_output=$(mktemp)\n{{ {}\n}} > \"$__kb_output\" 2>&1\n__kb_silenced=$?\n[ \"$__kb_silenced\" -eq 0 ] || cat \"$__kb_output\"\nrm -f \"$__kb_output\"\n{}",
+        cmd,
+        clean_up("__kb_silenced", &["__kb_output", "__kb_silenced"])
+    )
+}
+
+/// How long the entries of a timed out step get to exit after `SIGTERM`, before `SIGKILL`.
+const KILL_GRACE: Duration = Duration::from_secs(1);
+
+/// Sends `signal` to every process of the group `child` leads,
+/// so whatever the entry started goes away along with its shell, as with `timeout(1)`.
+fn signal_group(child: &std::process::Child, signal: &str) {
+    // The group may be gone already, which is just as good.
+    let _ = std::process::Command::new("kill")
+        .arg(format!("-{}", signal))
+        .arg("--")
+        .arg(format!("-{}", child.id()))
+        .stderr(Stdio::null())
+        .status();
+}
+
+/// Waits for `child`, killing its whole process group once `timeout` seconds went by.
+/// Returns `None` when it had to be killed.
+fn wait_with_timeout(
+    child: &mut std::process::Child,
+    timeout: Option<u64>,
+) -> Result<Option<std::process::ExitStatus>, Error> {
+    let Some(seconds) = timeout else {
+        return Ok(Some(child.wait()?));
+    };
+    let deadline = Instant::now() + Duration::from_secs(seconds);
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        if Instant::now() >= deadline {
+            signal_group(child, "TERM");
+            let grace = Instant::now() + KILL_GRACE;
+            while child.try_wait()?.is_none() && Instant::now() < grace {
+                std::thread::sleep(Duration::from_millis(50));
+            }
+            // Entries ignoring `SIGTERM`, or outliving the shell that started them.
+            signal_group(child, "KILL");
+            child.wait()?;
+            return Ok(None);
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
+
+pub fn execute(
+    steps: &[HookStep],
+    on_error: OnError,
+    timeout: Option<u64>,
+    log: &HookLog,
+    dir: &Path,
+) -> Result<(), Error> {
+    for step in steps {
+        if step.background {
+            std::process::Command::new("sh")
+                .arg("-c")
+                .arg(background(step, log))
+                .current_dir(dir)
+                .env(ACTIVE_PROJECT_VAR, dir)
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn()?;
+            continue;
+        }
+        let mut command = std::process::Command::new("sh");
+        command
+            .arg("-c")
+            .arg(&step.command)
+            .current_dir(dir)
+            .env(ACTIVE_PROJECT_VAR, dir);
+        // Its own process group lets a timeout kill everything the entry started.
+        if timeout.is_some() {
+            command.process_group(0);
+        }
+        let mut child = command.spawn()?;
+        let failure = match wait_with_timeout(&mut child, timeout)? {
+            Some(status) if status.success() => continue,
+            Some(status) => format!(
+                "failed with exit code {}",
+                status
+                    .code()
+                    .map_or_else(|| "unknown".to_string(), |c| c.to_string())
+            ),
+            None => format!(
+                "timed out after {} seconds and was killed",
+                timeout.unwrap_or_default()
+            ),
+        };
+        match on_error {
+            OnError::Stop => {
+                return Err(anyhow!("Hook entry {} {}.", step.name.bold(), failure));
+            }
+            OnError::Continue => {
+                eprintln!("Hook entry {} {}, continuing.", step.name.bold(), failure);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Collects the background entries that finished since the last time, clearing their status.
+pub fn take_reports(dir: &Path) -> Result<Vec<BackgroundReport>, Error> {
+    let mut reports = Vec::new();
+    if !dir.is_dir() {
+        return Ok(reports);
+    }
+    for file in std::fs::read_dir(dir)? {
+        let path = file?.path();
+        if path
+            .extension()
+            .is_none_or(|extension| extension != "status")
+        {
+            continue;
+        }
+        let project = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        // Renaming first keeps entries that finish meanwhile for the next report.
+        let taken = path.with_extension("status.taken");
+        std::fs::rename(&path, &taken)?;
+        let contents = std::fs::read_to_string(&taken)?;
+        std::fs::remove_file(&taken)?;
+        for line in contents.lines() {
+            if let Some((code, entry)) = line.split_once(' ') {
+                reports.push(BackgroundReport {
+                    project: project.clone(),
+                    entry: entry.to_string(),
+                    code: code.to_string(),
+                    log: HookLog::in_dir(dir, &project).output,
+                });
+            }
+        }
+    }
+    Ok(reports)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::script::Command;
+    use rand::distributions::Alphanumeric;
+    use rand::{thread_rng, Rng};
+
+    fn scripts() -> IndexMap<ScriptName, Script> {
+        let mut scripts = IndexMap::new();
+        for (name, cmd) in [
+            ("ok", "true"),
+            ("fail", "false"),
+            ("hello", "echo hello"),
+            ("slow", "sleep 5"),
+        ] {
+            let name = ScriptName::parse(name.into());
+            scripts.insert(name.clone(), Script::new(name, Command::parse(cmd.into())));
+        }
+        scripts
+    }
+
+    fn names(names: &[&str]) -> Vec<ScriptName> {
+        names
+            .iter()
+            .map(|n| ScriptName::parse(n.to_string()))
+            .collect()
+    }
+
+    fn entries(names: &[&str]) -> Vec<HookEntry> {
+        self::names(names)
+            .into_iter()
+            .map(HookEntry::Script)
+            .collect()
+    }
+
+    fn log() -> (PathBuf, HookLog) {
+        let rand_string: String = thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(10)
+            .map(char::from)
+            .collect();
+        let dir = std::env::temp_dir().join(format!("{}-krabby-logs", rand_string));
+        let log = HookLog::in_dir(&dir, "project");
+        (dir, log)
+    }
+
+    fn wait_for_reports(dir: &Path) -> Vec<BackgroundReport> {
+        for _ in 0..100 {
+            let reports = take_reports(dir).unwrap();
+            if !reports.is_empty() {
+                return reports;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(50));
+        }
+        panic!("Background entry never finished");
+    }
+
+    #[test]
+    fn hook_defaults_to_stop_on_error() {
+        let hook: ProjectHook = toml::from_str::<IndexMap<String, ProjectHook>>(
+            r#"
+            hook = { steps = ["ok"] }
+            "#,
+        )
+        .unwrap()
+        .shift_remove("hook")
+        .unwrap();
+        assert_eq!(hook.on_error(), OnError::Stop);
+        assert_eq!(
+            ProjectHook::ScriptArray(names(&["ok"])).on_error(),
+            OnError::Stop
+        );
+    }
+
+    #[test]
+    fn detailed_hook_parses_on_error_policy() {
+        let hook = toml::from_str::<IndexMap<String, ProjectHook>>(
+            r#"
+            [hook]
+            steps = ["ok", "fail"]
+            on_error = "continue"
+            "#,
+        )
+        .unwrap()
+        .shift_remove("hook")
+        .unwrap();
+        assert_eq!(
+            hook,
+            ProjectHook::Detailed(HookConfig {
+                steps: entries(&["ok", "fail"]),
+                on_error: OnError::Continue,
+                timeout: None,
+            })
+        );
+    }
+
+    #[test]
+    fn missing_script_is_an_error() {
+        let hook = ProjectHook::ScriptArray(names(&["missing"]));
+        assert!(hook.steps(&scripts()).is_err());
+        assert!(hook.render(&scripts(), &log().1).is_err());
+    }
+
+    #[test]
+    fn stop_policy_halts_shell_on_first_failure() {
+        let hook = ProjectHook::ScriptArray(names(&["ok", "fail", "hello"]));
+        let output = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(hook.render(&scripts(), &log().1).unwrap())
+            .output()
+            .unwrap();
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(stdout.contains("Hook entry fail failed with exit code 1."));
+        assert!(!stdout.contains("hello"));
+    }
+
+    #[test]
+    fn continue_policy_runs_every_entry_in_shell() {
+        let hook = ProjectHook::Detailed(HookConfig {
+            steps: entries(&["fail", "hello"]),
+            on_error: OnError::Continue,
+            timeout: None,
+        });
+        let output = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(hook.render(&scripts(), &log().1).unwrap())
+            .output()
+            .unwrap();
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(stdout.contains("Hook entry fail failed with exit code 1, continuing."));
+        assert!(stdout.contains("hello"));
+    }
+
+    #[test]
+    fn rendered_hooks_fail_when_an_entry_fails() {
+        for on_error in [OnError::Stop, OnError::Continue] {
+            let hook = ProjectHook::Detailed(HookConfig {
+                steps: entries(&["fail", "ok"]),
+                on_error,
+                timeout: None,
+            });
+            let status = std::process::Command::new("sh")
+                .arg("-c")
+                .arg(hook.render(&scripts(), &log().1).unwrap())
+                .status()
+                .unwrap();
+            assert!(!status.success());
+        }
+    }
+
+    #[test]
+    fn silenced_hooks_only_show_output_on_failure() {
+        let hook = ProjectHook::ScriptArray(names(&["hello", "ok"]));
+        let output = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(silence(&hook.render(&scripts(), &log().1).unwrap()))
+            .output()
+            .unwrap();
+        assert!(output.stdout.is_empty());
+
+        let hook = ProjectHook::ScriptArray(names(&["hello", "fail"]));
+        let output = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(silence(&hook.render(&scripts(), &log().1).unwrap()))
+            .output()
+            .unwrap();
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(stdout.contains("hello\n"));
+        assert!(stdout.contains("Hook entry fail failed with exit code 1."));
+    }
+
+    #[test]
+    fn execute_follows_the_same_policy() {
+        let stop = ProjectHook::ScriptArray(names(&["ok", "fail", "ok"]));
+        let err = stop
+            .execute(&scripts(), &log().1, &std::env::temp_dir())
+            .unwrap_err();
+        assert!(err.to_string().contains("fail"));
+
+        let cont = ProjectHook::Detailed(HookConfig {
+            steps: entries(&["fail", "ok"]),
+            on_error: OnError::Continue,
+            timeout: None,
+        });
+        assert!(cont
+            .execute(&scripts(), &log().1, &std::env::temp_dir())
+            .is_ok());
+    }
+
+    #[test]
+    fn timeout_kills_entries_in_shell() {
+        let hook = ProjectHook::Detailed(HookConfig {
+            steps: entries(&["slow", "hello"]),
+            on_error: OnError::Continue,
+            timeout: Some(1),
+        });
+        let output = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(hook.render(&scripts(), &log().1).unwrap())
+            .output()
+            .unwrap();
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(stdout
+            .contains("Hook entry slow timed out after 1 seconds and was killed, continuing."));
+        assert!(stdout.contains("hello"));
+    }
+
+    /// A directory with `sh` and `sleep`, and `timeout` under the name `timeout_name` if any,
+    /// to stand for the `PATH` of a system without GNU coreutils.
+    fn bare_path(timeout_name: Option<&str>) -> PathBuf {
+        let (dir, _) = log();
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut programs = vec![("sh", "sh"), ("sleep", "sleep")];
+        programs.extend(timeout_name.map(|name| ("timeout", name)));
+        for (program, name) in programs {
+            let output = std::process::Command::new("sh")
+                .arg("-c")
+                .arg(format!("command -v {}", program))
+                .output()
+                .unwrap();
+            let path = String::from_utf8(output.stdout).unwrap();
+            std::os::unix::fs::symlink(path.trim(), dir.join(name)).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn timeout_falls_back_to_gtimeout_or_no_limit() {
+        let hook = ProjectHook::Detailed(HookConfig {
+            steps: entries(&["slow", "hello"]),
+            on_error: OnError::Continue,
+            timeout: Some(1),
+        });
+        let run = |hook: &ProjectHook, path: &Path| {
+            let output = std::process::Command::new(path.join("sh"))
+                .arg("-c")
+                .arg(hook.render(&scripts(), &log().1).unwrap())
+                .env("PATH", path)
+                .output()
+                .unwrap();
+            String::from_utf8(output.stdout).unwrap()
+        };
+
+        let gtimeout = bare_path(Some("gtimeout"));
+        assert!(run(&hook, &gtimeout).contains("Hook entry slow timed out after 1 seconds"));
+        std::fs::remove_dir_all(gtimeout).unwrap();
+
+        let hook = ProjectHook::Detailed(HookConfig {
+            steps: entries(&["fail", "hello"]),
+            on_error: OnError::Continue,
+            timeout: Some(1),
+        });
+        let without = bare_path(None);
+        let output = run(&hook, &without);
+        assert!(output.contains("Neither timeout nor gtimeout was found"));
+        assert!(output.contains("Hook entry fail failed with exit code 1, continuing."));
+        assert!(output.contains("hello\n"));
+        std::fs::remove_dir_all(without).unwrap();
+    }
+
+    #[test]
+    fn timeout_kills_entries_on_execute() {
+        let hook = ProjectHook::Detailed(HookConfig {
+            steps: entries(&["slow", "hello"]),
+            on_error: OnError::Stop,
+            timeout: Some(1),
+        });
+        let started = Instant::now();
+        let err = hook
+            .execute(&scripts(), &log().1, &std::env::temp_dir())
+            .unwrap_err();
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(err.to_string().contains("timed out after 1 seconds"));
+    }
+
+    #[test]
+    fn generated_code_leaves_no_variables_behind() {
+        let hook = ProjectHook::Detailed(HookConfig {
+            steps: entries(&["hello", "fail"]),
+            on_error: OnError::Stop,
+            timeout: Some(5),
+        });
+        let code = hook.render(&scripts(), &log().1).unwrap();
+        for code in [code.clone(), silence(&code)] {
+            let output = std::process::Command::new("sh")
+                .arg("-c")
+                .arg(format!("{}\necho \"status $?\"\nset | grep '^__kb_'", code))
+                .output()
+                .unwrap();
+            let stdout = String::from_utf8(output.stdout).unwrap();
+            assert!(stdout.ends_with("status 1\n"), "{}", stdout);
+        }
+    }
+
+    #[test]
+    fn timeout_kills_what_entries_started() {
+        // `sh` waits for the subshell rather than running it in its place.
+        let (dir, _) = log();
+        std::fs::create_dir_all(&dir).unwrap();
+        let hook = ProjectHook::Detailed(HookConfig {
+            steps: vec![HookEntry::Inline {
+                run: "(sleep 2; touch survived); true".to_string(),
+                background: false,
+                when: None,
+            }],
+            on_error: OnError::Stop,
+            timeout: Some(1),
+        });
+
+        let err = hook.execute(&scripts(), &log().1, &dir).unwrap_err();
+        assert!(err.to_string().contains("timed out after 1 seconds"));
+        let output = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(hook.render(&scripts(), &log().1).unwrap())
+            .current_dir(&dir)
+            .output()
+            .unwrap();
+        assert!(String::from_utf8(output.stdout)
+            .unwrap()
+            .contains("timed out after 1 seconds"));
+        std::thread::sleep(Duration::from_secs(3));
+        assert!(!dir.join("survived").exists());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn hook_entries_parse_background_setting() {
+        let hook = toml::from_str::<IndexMap<String, ProjectHook>>(
+            r#"
+            hook = { steps = ["ok", { script = "hello", background = true }] }
+            "#,
+        )
+        .unwrap()
+        .shift_remove("hook")
+        .unwrap();
+        let steps = hook.steps(&scripts()).unwrap();
+        assert!(!steps[0].background);
+        assert!(steps[1].background);
+        assert_eq!(
+            hook.script_names(),
+            names(&["ok", "hello"]).iter().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn conditional_entries_run_only_when_conditions_hold() {
+        let (dir, log) = log();
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("package-lock.json"), "{}").unwrap();
+        let hook = toml::from_str::<IndexMap<String, ProjectHook>>(
+            r#"
+            hook = [
+                { run = "echo install", when = { file_newer = ["package-lock.json", "node_modules"] } },
+                { run = "echo outside-ci", when = { env_unset = "KRABBY_TEST_CI" } },
+                { script = "hello", when = { branch = "no-such-branch" } },
+            ]
+            "#,
+        )
+        .unwrap()
+        .shift_remove("hook")
+        .unwrap();
+        let output = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(hook.render(&scripts(), &log).unwrap())
+            .current_dir(&dir)
+            .env_remove(ACTIVE_PROJECT_VAR)
+            .env("KRABBY_TEST_CI", "1")
+            .output()
+            .unwrap();
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(stdout.contains("install\n"));
+        assert!(stdout.contains("Skipping hook entry echo outside-ci"));
+        assert!(stdout.contains("Skipping hook entry hello"));
+
+        std::fs::create_dir(dir.join("node_modules")).unwrap();
+        let output = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(hook.render(&scripts(), &log).unwrap())
+            .current_dir(&dir)
+            .env_remove(ACTIVE_PROJECT_VAR)
+            .env_remove("KRABBY_TEST_CI")
+            .output()
+            .unwrap();
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(stdout.contains("Skipping hook entry echo install"));
+        assert!(stdout.contains("outside-ci\n"));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn conditions_are_checked_from_the_project_directory() {
+        let (dir, log) = log();
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::write(dir.join("package-lock.json"), "{}").unwrap();
+        std::fs::create_dir(dir.join("src/node_modules")).unwrap();
+        let hook = ProjectHook::Mixed(vec![HookEntry::Inline {
+            run: "echo install".into(),
+            background: false,
+            when: Some(Condition {
+                file_newer: Some(["package-lock.json".into(), "node_modules".into()]),
+                ..Default::default()
+            }),
+        }]);
+        // Entered through a subdirectory, with a node_modules of its own.
+        let output = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(hook.render(&scripts(), &log).unwrap())
+            .current_dir(dir.join("src"))
+            .env(ACTIVE_PROJECT_VAR, &dir)
+            .output()
+            .unwrap();
+        assert_eq!(String::from_utf8(output.stdout).unwrap(), "install\n");
+
+        std::fs::create_dir(dir.join("node_modules")).unwrap();
+        let output = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(hook.render(&scripts(), &log).unwrap())
+            .current_dir(dir.join("src"))
+            .env(ACTIVE_PROJECT_VAR, &dir)
+            .output()
+            .unwrap();
+        assert!(String::from_utf8(output.stdout)
+            .unwrap()
+            .contains("Skipping hook entry echo install"));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn conditions_reject_unknown_keys_and_invalid_variables() {
+        assert!(toml::from_str::<Condition>("branche = \"main\"").is_err());
+        let condition = Condition {
+            env_unset: Some("$(rm -rf)".into()),
+            ..Default::default()
+        };
+        assert!(condition.test().is_err());
+    }
+
+    #[test]
+    fn hook_entries_reject_misspelled_keys() {
+        for entry in [
+            "{ script = \"install\", backgrond = true }",
+            "{ run = \"make\", whn = { branch = \"main\" } }",
+        ] {
+            assert!(toml::from_str::<IndexMap<String, ProjectHook>>(&format!(
+                "hook = [{}]",
+                entry
+            ))
+            .is_err());
+        }
+        assert!(toml::from_str::<IndexMap<String, ProjectHook>>(
+            "[hook]\nsteps = [\"install\"]\non_eror = \"continue\""
+        )
+        .is_err());
+        assert!(toml::from_str::<IndexMap<String, ProjectHook>>(
+            "hook = [{ script = \"install\", background = true }]"
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn hook_mixes_scripts_and_inline_commands() {
+        let hook = toml::from_str::<IndexMap<String, ProjectHook>>(
+            r#"
+            hook = ["hello", { script = "ok" }, { run = "echo inline" }]
+            "#,
+        )
+        .unwrap()
+        .shift_remove("hook")
+        .unwrap();
+        assert_eq!(
+            hook,
+            ProjectHook::Mixed(vec![
+                HookEntry::Script(ScriptName::parse("hello".into())),
+                HookEntry::Detailed {
+                    script: ScriptName::parse("ok".into()),
+                    background: false,
+                    when: None,
+                },
+                HookEntry::Inline {
+                    run: "echo inline".into(),
+                    background: false,
+                    when: None,
+                },
+            ])
+        );
+        assert_eq!(
+            hook.script_names(),
+            names(&["hello", "ok"]).iter().collect::<Vec<_>>()
+        );
+
+        let output = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(hook.render(&scripts(), &log().1).unwrap())
+            .output()
+            .unwrap();
+        assert_eq!(String::from_utf8(output.stdout).unwrap(), "hello\ninline\n");
+    }
+
+    #[test]
+    fn entries_from_input_are_scripts_unless_inline() {
+        assert_eq!(
+            HookEntry::from_input("hello", false),
+            HookEntry::Script(ScriptName::parse("hello".into()))
+        );
+        assert_eq!(
+            HookEntry::from_input("echo \"hi\"", true),
+            HookEntry::Inline {
+                run: "echo \"hi\"".into(),
+                background: false,
+                when: None,
+            }
+        );
+    }
+
+    #[test]
+    fn adding_entries_keeps_the_simplest_form() {
+        let script = |name: &str| HookEntry::from_input(name, name.contains(' '));
+
+        let hook = ProjectHook::from_entry(script("hello")).add_entry(script("ok"));
+        assert_eq!(hook, ProjectHook::ScriptArray(names(&["hello", "ok"])));
+
+        let hook = hook.add_entry(script("nvm use"));
+        assert_eq!(
+            hook,
+            ProjectHook::Mixed(vec![script("hello"), script("ok"), script("nvm use")])
+        );
+
+        let hook = ProjectHook::from_entry(script("nvm use"));
+        assert_eq!(hook, ProjectHook::Simple("nvm use".into()));
+        assert_eq!(
+            hook.add_entry(script("hello")),
+            ProjectHook::Mixed(vec![script("nvm use"), script("hello")])
+        );
+    }
+
+    #[test]
+    fn removing_entries_drops_empty_hooks() {
+        let hook = ProjectHook::Mixed(vec![
+            HookEntry::from_input("hello", false),
+            HookEntry::from_input("nvm use", true),
+        ]);
+        assert!(hook.remove_entry("missing").is_err());
+
+        let hook = ProjectHook::Mixed(vec![
+            HookEntry::from_input("hello", false),
+            HookEntry::from_input("nvm use", true),
+        ]);
+        let hook = hook.remove_entry("nvm use").unwrap().unwrap();
+        assert_eq!(hook.remove_entry("hello").unwrap(), None);
+
+        let hook = ProjectHook::ScriptArray(names(&["hello", "ok"]));
+        assert_eq!(
+            hook.remove_entry("ok").unwrap(),
+            Some(ProjectHook::ScriptArray(names(&["hello"])))
+        );
+        assert_eq!(
+            ProjectHook::Simple("nvm use".into())
+                .remove_entry("nvm use")
+                .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn plain_script_arrays_keep_their_form() {
+        let hook = toml::from_str::<IndexMap<String, ProjectHook>>(
+            r#"
+            hook = ["hello", "ok"]
+            "#,
+        )
+        .unwrap()
+        .shift_remove("hook")
+        .unwrap();
+        assert_eq!(hook, ProjectHook::ScriptArray(names(&["hello", "ok"])));
+    }
+
+    #[test]
+    fn background_entries_are_detached_and_reported() {
+        let hook = ProjectHook::Detailed(HookConfig {
+            steps: vec![
+                HookEntry::Detailed {
+                    script: ScriptName::parse("hello".into()),
+                    background: true,
+                    when: None,
+                },
+                HookEntry::Script(ScriptName::parse("ok".into())),
+            ],
+            on_error: OnError::Stop,
+            timeout: None,
+        });
+        let (dir, log) = log();
+        let output = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(hook.render(&scripts(), &log).unwrap())
+            .output()
+            .unwrap();
+        assert!(!String::from_utf8(output.stdout).unwrap().contains("hello"));
+
+        let reports = wait_for_reports(&dir);
+        assert_eq!(
+            reports,
+            vec![BackgroundReport {
+                project: "project".into(),
+                entry: "hello".into(),
+                code: "0".into(),
+                log: log.output.clone(),
+            }]
+        );
+        assert!(reports[0].success());
+        assert_eq!(std::fs::read_to_string(&log.output).unwrap(), "hello\n");
+        assert!(take_reports(&dir).unwrap().is_empty());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn execute_detaches_background_entries_too() {
+        let hook = ProjectHook::Detailed(HookConfig {
+            steps: vec![HookEntry::Detailed {
+                script: ScriptName::parse("fail".into()),
+                background: true,
+                when: None,
+            }],
+            on_error: OnError::Stop,
+            timeout: None,
+        });
+        let (dir, log) = log();
+        assert!(hook
+            .execute(&scripts(), &log, &std::env::temp_dir())
+            .is_ok());
+
+        let reports = wait_for_reports(&dir);
+        assert_eq!(reports[0].entry, "fail");
+        assert!(!reports[0].success());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
 }
@@ -0,0 +1,263 @@
+use crate::commands::ImportSource;
+use anyhow::{anyhow, Error};
+use indexmap::IndexMap;
+use owo_colors::OwoColorize;
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// A directory another tool knew about, and its score when the tool keeps one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Imported {
+    pub path: PathBuf,
+    pub score: Option<f64>,
+}
+
+/// Reads what `source` knows about from `file`, or from where the tool keeps it otherwise.
+/// zoxide and ghq are asked directly, as their own commands are the way to read them.
+pub fn read(source: ImportSource, file: Option<&Path>) -> Result<Vec<Imported>, Error> {
+    let contents = match (file, source) {
+        (Some(file), _) => std::fs::read_to_string(file)
+            .map_err(|e| anyhow!("Failed to read {}.\n{}", file.to_string_lossy().bold(), e))?,
+        (None, ImportSource::Zoxide) => run("zoxide", &["query", "--list", "--score"])?,
+        (None, ImportSource::Ghq) => run("ghq", &["list", "--full-path"])?,
+        (None, ImportSource::Autojump) => read_default(&autojump_file())?,
+        (None, ImportSource::Projectile) => read_default(&projectile_file())?,
+    };
+    Ok(match source {
+        ImportSource::Zoxide => parse_scored(&contents, char::is_whitespace),
+        ImportSource::Autojump => parse_scored(&contents, |c| c == '\t'),
+        ImportSource::Ghq => parse_list(&contents),
+        ImportSource::Projectile => parse_projectile(&contents),
+    })
+}
+
+/// The directories worth registering out of what was imported.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Directories {
+    /// Each directory once, as `add_project` would register it.
+    pub paths: Vec<PathBuf>,
+    /// The visits the score of each directory stands for, when it had one.
+    pub visits: IndexMap<PathBuf, u64>,
+    /// How many directories no longer exist.
+    pub missing: usize,
+}
+
+/// Keeps the directories of `imported` that still exist, leaving out those scored lower than `min_score`.
+pub fn directories(imported: Vec<Imported>, min_score: Option<f64>) -> Directories {
+    let mut directories = Directories::default();
+    for imported in imported {
+        let too_low = match (imported.score, min_score) {
+            (Some(score), Some(min_score)) => score < min_score,
+            _ => false,
+        };
+        if too_low {
+            continue;
+        }
+        let Ok(path) = std::fs::canonicalize(&imported.path) else {
+            directories.missing += 1;
+            continue;
+        };
+        if !path.is_dir() || directories.paths.contains(&path) {
+            continue;
+        }
+        if let Some(score) = imported.score {
+            directories
+                .visits
+                .insert(path.clone(), score.round().max(1.0) as u64);
+        }
+        directories.paths.push(path);
+    }
+    directories
+}
+
+fn run(program: &str, args: &[&str]) -> Result<String, Error> {
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .map_err(|e| anyhow!("Failed to run {}.\n{}", program.bold(), e))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "{} failed:\n{}",
+            format!("{} {}", program, args.join(" ")).bold(),
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+fn read_default(path: &Path) -> Result<String, Error> {
+    std::fs::read_to_string(path).map_err(|e| {
+        anyhow!(
+            "Failed to read {}. Pass the file to import if it is somewhere else.\n{}",
+            path.to_string_lossy().bold(),
+            e
+        )
+    })
+}
+
+/// Where autojump keeps its database, usually `~/.local/share/autojump/autojump.txt`.
+fn autojump_file() -> PathBuf {
+    dirs::data_dir()
+        .unwrap_or_default()
+        .join("autojump")
+        .join("autojump.txt")
+}
+
+/// Where projectile keeps its known projects, in either of the usual Emacs directories.
+fn projectile_file() -> PathBuf {
+    let home = dirs::home_dir().unwrap_or_default();
+    let xdg = home.join(".config/emacs/projectile-bookmarks.eld");
+    match xdg.is_file() {
+        true => xdg,
+        false => home.join(".emacs.d/projectile-bookmarks.eld"),
+    }
+}
+
+/// Reads lines with a score and a path, separated as `separator` tells,
+/// like `zoxide query --list --score` or the autojump database print them.
+fn parse_scored(contents: &str, separator: impl Fn(char) -> bool + Copy) -> Vec<Imported> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let (score, path) = line.trim_start().split_once(separator)?;
+            Some(Imported {
+                path: PathBuf::from(path.trim_start()),
+                score: Some(score.parse().ok()?),
+            })
+        })
+        .collect()
+}
+
+/// Reads a path per line, like `ghq list --full-path` prints them.
+fn parse_list(contents: &str) -> Vec<Imported> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| Imported {
+            path: PathBuf::from(line),
+            score: None,
+        })
+        .collect()
+}
+
+/// Reads the list of strings projectile writes, like `("~/code/api/" "/srv/web/")`.
+fn parse_projectile(contents: &str) -> Vec<Imported> {
+    contents
+        .split('"')
+        // Strings are every other piece, between the quotes.
+        .skip(1)
+        .step_by(2)
+        .filter(|path| !path.is_empty())
+        .map(|path| Imported {
+            path: expand_home(path),
+            score: None,
+        })
+        .collect()
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match path.strip_prefix("~/") {
+        Some(path) => dirs::home_dir().unwrap_or_default().join(path),
+        None => PathBuf::from(path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn imported(path: &str, score: Option<f64>) -> Imported {
+        Imported {
+            path: PathBuf::from(path),
+            score,
+        }
+    }
+
+    #[test]
+    fn zoxide_scores_are_read() {
+        let output = "  12.5 /home/me/code/api\n   4 /home/me/my notes\nnot a score\n";
+        assert_eq!(
+            parse_scored(output, char::is_whitespace),
+            vec![
+                imported("/home/me/code/api", Some(12.5)),
+                imported("/home/me/my notes", Some(4.0))
+            ]
+        );
+    }
+
+    #[test]
+    fn autojump_weights_are_read() {
+        let contents = "22.36\t/home/me/code/api\n10.0\t/home/me/my notes\n";
+        assert_eq!(
+            parse_scored(contents, |c| c == '\t'),
+            vec![
+                imported("/home/me/code/api", Some(22.36)),
+                imported("/home/me/my notes", Some(10.0))
+            ]
+        );
+    }
+
+    #[test]
+    fn ghq_paths_are_read() {
+        let output = "/home/me/ghq/github.com/me/api\n\n/home/me/ghq/github.com/me/web\n";
+        assert_eq!(
+            parse_list(output),
+            vec![
+                imported("/home/me/ghq/github.com/me/api", None),
+                imported("/home/me/ghq/github.com/me/web", None)
+            ]
+        );
+    }
+
+    #[test]
+    fn projectile_bookmarks_are_read() {
+        let contents = "(\"~/code/api/\" \"/srv/web/\")";
+        let home = dirs::home_dir().unwrap();
+        assert_eq!(
+            parse_projectile(contents),
+            vec![
+                Imported {
+                    path: home.join("code/api/"),
+                    score: None
+                },
+                imported("/srv/web/", None)
+            ]
+        );
+    }
+
+    #[test]
+    fn directories_are_filtered_by_score() {
+        let tmp = std::fs::canonicalize(std::env::temp_dir()).unwrap();
+        let imported = vec![
+            imported(&tmp.to_string_lossy(), Some(12.4)),
+            imported("/no/such/krabby/directory", Some(30.0)),
+            imported("/", Some(0.2)),
+            imported(&tmp.join(".").to_string_lossy(), Some(3.0)),
+        ];
+        assert_eq!(
+            directories(imported.clone(), Some(1.0)),
+            Directories {
+                paths: vec![tmp.clone()],
+                visits: IndexMap::from([(tmp.clone(), 12)]),
+                missing: 1,
+            }
+        );
+        let all = directories(imported, None);
+        assert_eq!(all.paths, vec![tmp, PathBuf::from("/")]);
+        assert_eq!(all.visits.get(Path::new("/")), Some(&1));
+    }
+
+    #[test]
+    fn files_are_read_instead_of_asking_the_tool() {
+        let file = std::env::temp_dir().join(format!("krabby-import-{}", std::process::id()));
+        std::fs::write(&file, "3 /srv/api\n").unwrap();
+        assert_eq!(
+            read(ImportSource::Zoxide, Some(&file)).unwrap(),
+            vec![imported("/srv/api", Some(3.0))]
+        );
+        std::fs::remove_file(file).unwrap();
+    }
+}
@@ -1,7 +1,15 @@
 pub mod commands;
+pub mod config;
 pub mod database;
+pub mod doctor;
+pub mod env;
 pub mod hook;
+pub mod import;
+pub mod matching;
 pub mod messages;
+pub mod portable;
 pub mod project;
+pub mod scan;
 pub mod script;
 pub mod shell;
+pub mod trust;
@@ -1,14 +1,25 @@
-use std::{io::Error, path::PathBuf, process::exit};
+use std::{
+    io::{Error, IsTerminal, Write},
+    path::{Path, PathBuf},
+    process::exit,
+};
 
 use clap::Parser;
+use indexmap::IndexMap;
 use krabby_cli::{
     commands::*,
-    database::Database,
-    hook::ProjectHook,
+    config::{self, Config, Verbosity},
+    database::{self, Database, ProjectEntry, Resolution, ACTIVE_PROJECT_VAR},
+    doctor::{self, Problem},
+    hook::{self, ProjectHook},
+    import,
     messages::Message,
+    portable,
     project::{self, ProjectName},
+    scan::{self, Proposal},
     script,
     shell::{self, Flavor},
+    trust::Trust,
 };
 use owo_colors::OwoColorize;
 
@@ -25,10 +36,352 @@ struct Cli {
     #[arg(global = true, short = 'f', long, value_name = "PROJECT_FILE")]
     project_file: Option<PathBuf>,
 
+    /// Specify config.toml with your own settings
+    #[arg(global = true, short, long, value_name = "CONFIG")]
+    config: Option<PathBuf>,
+
+    /// Only tell about what went wrong when entering or leaving projects
+    #[arg(global = true, short, long, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Tell everything krabby does when entering or leaving projects
+    #[arg(global = true, short, long)]
+    verbose: bool,
+
     #[command(subcommand)]
     command: Commands,
 }
 
+/// Takes the shell to `destination`, a project that may be followed by a place or
+/// a subdirectory as in `api:web` or `api/src/handlers`, or given `within` on its own.
+fn jump(mut database: Database, config: Config, destination: &str, within: Option<String>) -> ! {
+    let (query, mut destination) = database::split_destination(destination);
+    let project_name = resolve_project(&database, query);
+    if let Some(within) = within {
+        destination.push(&within, &database.places(&project_name));
+    }
+    database.set_config(config);
+    match database.go_to_project(&project_name, &destination, active_project().as_deref()) {
+        Ok(cmd) => {
+            print!("{}", cmd);
+            database.save();
+            if database.config.verbosity >= Verbosity::Normal {
+                print!("{}", shell::echo("You're good to go!"));
+            }
+            exit(0);
+        }
+        Err(e) => {
+            println!("{}", e);
+            exit(1);
+        }
+    }
+}
+
+/// Asks the user `question`, taking anything but a yes as a no.
+fn confirm(question: &str) -> bool {
+    print!("{} [y/N] ", question);
+    std::io::stdout()
+        .flush()
+        .expect("Failed to write question.");
+    let mut answer = String::new();
+    std::io::stdin()
+        .read_line(&mut answer)
+        .expect("Failed to read answer.");
+    matches!(answer.trim(), "y" | "Y" | "yes")
+}
+
+/// Fixes the `problems` that can be fixed, asking about each one unless `yes` was given.
+fn prune(mut database: Database, problems: Vec<(ProjectName, Problem)>, yes: bool) -> ! {
+    let interactive = std::io::stdin().is_terminal();
+    let fixable: Vec<&(ProjectName, Problem)> = problems
+        .iter()
+        .filter(|(_, problem)| problem.is_fixable())
+        .collect();
+    if fixable.is_empty() {
+        println!("There is nothing krabby can fix by itself.");
+        exit(0);
+    }
+    if !yes && !interactive {
+        println!(
+            "There are {} problems krabby can fix. Run it with {} to fix them without asking.",
+            fixable.len(),
+            "--yes".bold()
+        );
+        exit(1);
+    }
+    let mut fixed = 0;
+    for (project_name, problem) in fixable.iter().copied() {
+        if !yes {
+            println!("{}: {}", project_name.bold(), problem);
+            let question = problem.fix_question(project_name).unwrap();
+            if !confirm(&question) {
+                continue;
+            }
+        }
+        match doctor::fix(&mut database, project_name, problem) {
+            Ok(_) => fixed += 1,
+            Err(e) => println!("Failed to fix {}!\n{}", project_name.bold(), e),
+        }
+    }
+    if fixed > 0 {
+        database.save();
+    }
+    println!("Fixed {} of {} problems.", fixed, fixable.len());
+    exit(0);
+}
+
+/// Registers the candidates of `proposal` the user picks, or all of them with `yes`,
+/// after telling about the directories that were left out.
+/// Projects in `visits` keep the visits another tool counted for them.
+fn register_all(
+    mut database: Database,
+    proposal: Proposal,
+    visits: &IndexMap<PathBuf, u64>,
+    yes: bool,
+) -> ! {
+    if !proposal.registered.is_empty() {
+        println!(
+            "Skipping {} directories that are registered already.",
+            proposal.registered.len()
+        );
+    }
+    for (project_name, path) in &proposal.collisions {
+        println!(
+            "{} is {}, so {} was left out. Register it under another name with {}.",
+            project_name.bold(),
+            "taken".red().bold(),
+            path.to_string_lossy().bold(),
+            "'kb project add PROJECT_NAME PROJECT_PATH'".bold()
+        );
+    }
+    for path in &proposal.unnamed {
+        println!(
+            "{} {}, so it was left out.",
+            path.to_string_lossy().bold(),
+            "has no usable name".red().bold()
+        );
+    }
+    if proposal.candidates.is_empty() {
+        println!("There is nothing new to register.");
+        exit(0);
+    }
+    for (i, (project_name, path)) in proposal.candidates.iter().enumerate() {
+        println!(
+            "  {}) {} at {}",
+            i + 1,
+            project_name.bold(),
+            path.to_string_lossy()
+        );
+    }
+    let picked = match yes {
+        true => (0..proposal.candidates.len()).collect(),
+        false if !std::io::stdin().is_terminal() => {
+            println!(
+                "Run it with {} to register them without asking.",
+                "--yes".bold()
+            );
+            exit(1);
+        }
+        false => {
+            print!("Which ones should krabby register? (all, none or numbers like 1 3-5) [all] ");
+            std::io::stdout()
+                .flush()
+                .expect("Failed to write question.");
+            let mut answer = String::new();
+            std::io::stdin()
+                .read_line(&mut answer)
+                .expect("Failed to read answer.");
+            scan::parse_selection(&answer, proposal.candidates.len()).unwrap_or_else(|e| {
+                println!("{}", e);
+                exit(1);
+            })
+        }
+    };
+    let mut registered = 0;
+    for index in picked {
+        let (project_name, path) = proposal.candidates[index].clone();
+        match database.add_project(project_name.clone(), path.clone()) {
+            Ok(_) => {
+                if let Some(visits) = visits.get(&path) {
+                    database.record_visits(&project_name, *visits);
+                }
+                println!("{}", Message::RegisterProjectSuccess(project_name));
+                registered += 1;
+            }
+            Err(e) => println!("{}", Message::RegisterProjectFail(project_name, e)),
+        }
+    }
+    if registered > 0 {
+        database.save();
+    }
+    exit(0);
+}
+
+/// The project or alias registered as `name`, which may predate the rules names follow now,
+/// so it can still be renamed or removed. Other names have to be valid.
+fn registered_name(database: &Database, name: String) -> ProjectName {
+    database
+        .projects
+        .iter()
+        .flat_map(|(project_name, entry)| std::iter::once(project_name).chain(&entry.aliases))
+        .find(|registered| registered.to_string() == name)
+        .cloned()
+        .unwrap_or_else(|| ProjectName::parse(name))
+}
+
+/// Finds the project the user meant with `query`, asking them to pick one
+/// when several match just as well.
+fn resolve_project(database: &Database, query: &str) -> ProjectName {
+    let candidates = match database.resolve_project(query) {
+        Resolution::Found(project_name) => return project_name,
+        Resolution::Ambiguous(candidates) => candidates,
+        Resolution::NotFound(suggestions) => {
+            println!(
+                "{}",
+                Message::ProjectNotFound(query.to_string(), suggestions)
+            );
+            exit(1);
+        }
+    };
+    // The shell integration reads what we print, so the list goes to stderr.
+    let interactive = std::io::stdin().is_terminal() && std::io::stderr().is_terminal();
+    if !interactive {
+        return candidates[0].clone();
+    }
+    eprintln!("Several projects match {}:", query.bold());
+    for (i, project_name) in candidates.iter().enumerate() {
+        let path = database.get_project_path(project_name).unwrap();
+        eprintln!(
+            "  {}) {} at {}",
+            i + 1,
+            project_name.bold(),
+            path.to_string_lossy()
+        );
+    }
+    eprint!("Which one? [1] ");
+    let mut answer = String::new();
+    std::io::stdin()
+        .read_line(&mut answer)
+        .expect("Failed to read answer.");
+    let choice = match answer.trim() {
+        "" => 1,
+        answer => answer.parse::<usize>().unwrap_or(0),
+    };
+    match candidates.get(choice.wrapping_sub(1)) {
+        Some(project_name) => project_name.clone(),
+        None => {
+            println!("{} is not one of the options.", answer.trim().bold());
+            exit(1);
+        }
+    }
+}
+
+/// Runs the `post_register` hook of the user and the `on_init` hook of the project,
+/// from the directory of the project that was just registered.
+fn after_register(database: &Database, project_name: &ProjectName) -> ! {
+    let project_path = database
+        .get_project_path(project_name)
+        .expect("Failed to find the project that was just registered")
+        .clone();
+    let mut success = true;
+    if let Some(post_register) = &database.config.hooks.post_register {
+        let status = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(config::expand(post_register, project_name, &project_path))
+            .current_dir(&project_path)
+            .status()
+            .expect("Failed to run post_register hook.");
+        if !status.success() {
+            println!(
+                "The {} hook {} {}.",
+                "post_register".bold(),
+                "failed with".red().bold(),
+                status.red().bold()
+            );
+            success = false;
+        }
+    }
+    // Projects are not required to have a project file.
+    let project = project::Project::from_file(project_path.join("krabby.toml")).ok();
+    if let Some((project, on_init)) = project
+        .as_ref()
+        .and_then(|project| project.on_init().as_ref().map(|on_init| (project, on_init)))
+    {
+        match database.trust(&project_path, project) {
+            Ok(Trust::Allowed) => {
+                if let Err(e) = on_init.execute(project.scripts(), &project.log(), &project_path) {
+                    println!("{}", e);
+                    success = false;
+                }
+            }
+            Ok(_) => Message::InitNotAllowed(project.name.clone()).println(),
+            Err(e) => {
+                Message::HookBroken(project.name.clone(), e).println();
+                success = false;
+            }
+        }
+    }
+    exit(if success { 0 } else { 1 })
+}
+
+/// Sets the hook of `project`, saves it and prints what it runs now.
+fn update_hook(mut project: project::Project, hook: Option<ProjectHook>) -> ! {
+    match project.set_hook(hook) {
+        Ok(Some(_)) => {
+            project.save();
+            println!(
+                "The hook was set {}!\n{}",
+                "successfully".green().bold(),
+                "[Hook]:".green().bold()
+            );
+            print_hook_steps(&project);
+            exit(0);
+        }
+        Ok(None) => {
+            project.save();
+            println!("The hook was {}", "removed.".red().bold());
+            exit(0);
+        }
+        Err(e) => {
+            println!("Failed to set hook.\n{}", e);
+            exit(1)
+        }
+    }
+}
+
+/// Lists the commands the hook of `project` resolves to, entry by entry.
+fn print_hook_steps(project: &project::Project) {
+    let Some(hook) = project.hook() else {
+        return;
+    };
+    match hook.steps(project.scripts()) {
+        Ok(steps) => {
+            for step in steps {
+                let background = if step.background { " &" } else { "" };
+                match step.name == step.command {
+                    true => println!("\t- {}{}", step.command.bold(), background),
+                    false => println!("\t- {}: {}{}", step.name, step.command.bold(), background),
+                }
+            }
+        }
+        Err(e) => println!("{}", e),
+    }
+}
+
+/// Directory of the project the calling shell is in, as exported on entry.
+fn active_project() -> Option<PathBuf> {
+    std::env::var_os(ACTIVE_PROJECT_VAR).map(PathBuf::from)
+}
+
+/// Directory holding the project file, as registered on the database.
+fn project_dir(project_file_path: &Path) -> PathBuf {
+    std::fs::canonicalize(project_file_path)
+        .expect("Failed to canonicalize project file path")
+        .parent()
+        .expect("Failed to get project directory")
+        .to_path_buf()
+}
+
 fn main() -> Result<(), Error> {
     let cli = Cli::parse();
 
@@ -43,22 +396,23 @@ fn main() -> Result<(), Error> {
     }
 
     let project_file_path = cli.project_file.or(Some(PathBuf::from("./krabby.toml")));
+    let mut config = Config::from_file(&cli.config.unwrap_or_else(Config::default_path))
+        .expect("Failed to read config.");
+    if cli.quiet {
+        config.verbosity = Verbosity::Quiet;
+    }
+    if cli.verbose {
+        config.verbosity = Verbosity::Verbose;
+    }
 
     match cli.command {
-        Commands::Cd { project_name } => {
-            let project_name = project::ProjectName::parse(project_name);
+        Commands::Cd {
+            project_name,
+            within,
+        } => {
             let database = Database::from_file(database_path.unwrap())
                 .expect("Failed to read krabby database.");
-            match database.go_to_project(&project_name) {
-                Ok(_) => {
-                    println!("echo \"You're good to go!\"");
-                    exit(0);
-                }
-                Err(e) => {
-                    println!("{}", e);
-                    exit(1);
-                }
-            }
+            jump(database, config, &project_name, within);
         }
         Commands::Project(project) => {
             let project_cmd = project.command;
@@ -72,9 +426,10 @@ fn main() -> Result<(), Error> {
                     let project_name = ProjectName::parse(project_name);
                     match database.add_project(project_name.clone(), project_path.into()) {
                         Ok(_) => {
-                            println!("{}", Message::RegisterProjectSuccess(project_name));
+                            println!("{}", Message::RegisterProjectSuccess(project_name.clone()));
                             database.save();
-                            exit(0);
+                            database.set_config(config);
+                            after_register(&database, &project_name);
                         }
                         Err(e) => {
                             println!("{}", Message::RegisterProjectFail(project_name, e));
@@ -82,7 +437,7 @@ fn main() -> Result<(), Error> {
                         }
                     }
                 }
-                ProjectCommands::List => {
+                ProjectCommands::List { sort } => {
                     let database = Database::from_file(database_path.unwrap())
                         .expect("Failed to read krabby database.");
                     if database.projects.is_empty() {
@@ -93,12 +448,28 @@ fn main() -> Result<(), Error> {
                         exit(0);
                     }
                     println!("So, let's take a look at your projects!");
-                    for (name, projects) in database.projects {
-                        println!(
-                            "{} at {}",
-                            name.bold(),
-                            projects.into_os_string().to_str().unwrap().bold()
-                        );
+                    let projects: Vec<(&ProjectName, &ProjectEntry)> = match sort {
+                        SortBy::Registration => database.projects.iter().collect(),
+                        SortBy::Name => {
+                            let mut projects: Vec<_> = database.projects.iter().collect();
+                            projects.sort_by_key(|(name, _)| name.to_string());
+                            projects
+                        }
+                        SortBy::Frecency => database.ranked_projects(database::now()),
+                    };
+                    for (name, entry) in projects {
+                        println!("{} at {}", name.bold(), entry.path.to_str().unwrap().bold());
+                        if !entry.aliases.is_empty() {
+                            let aliases: Vec<String> =
+                                entry.aliases.iter().map(ProjectName::to_string).collect();
+                            println!("\taka {}", aliases.join(", "));
+                        }
+                        if let Some(description) = &entry.description {
+                            println!("\t{}", description);
+                        }
+                        if !entry.tags.is_empty() {
+                            println!("\t[{}]", entry.tags.join(", "));
+                        }
                     }
                     println!("And that's a wrap!");
                     exit(0);
@@ -106,7 +477,8 @@ fn main() -> Result<(), Error> {
                 ProjectCommands::Remove { project_name } => {
                     let mut database = Database::from_file(database_path.unwrap())
                         .expect("Failed to read krabby database");
-                    match database.remove_project(ProjectName::parse(project_name.clone())) {
+                    match database.remove_project(registered_name(&database, project_name.clone()))
+                    {
                         Ok(_) => {
                             println!(
                                 "Project {} was removed from the database.",
@@ -121,6 +493,267 @@ fn main() -> Result<(), Error> {
                         }
                     }
                 }
+                ProjectCommands::Alias {
+                    alias,
+                    project_name,
+                } => {
+                    let mut database = Database::from_file(database_path.unwrap())
+                        .expect("Failed to read krabby database.");
+                    let project_name = ProjectName::parse(project_name);
+                    let alias = ProjectName::parse(alias);
+                    match database.alias_project(&project_name, alias.clone()) {
+                        Ok(_) => {
+                            database.save();
+                            println!(
+                                "{} can now be reached as {} too.",
+                                project_name.bold(),
+                                alias.bold()
+                            );
+                            exit(0);
+                        }
+                        Err(e) => {
+                            println!("Failed to add alias!\n{}", e);
+                            exit(1);
+                        }
+                    }
+                }
+                ProjectCommands::Unalias { alias } => {
+                    let mut database = Database::from_file(database_path.unwrap())
+                        .expect("Failed to read krabby database.");
+                    let alias = registered_name(&database, alias);
+                    match database.unalias_project(&alias) {
+                        Ok(project_name) => {
+                            database.save();
+                            println!(
+                                "{} is no longer an alias of {}.",
+                                alias.bold(),
+                                project_name.bold()
+                            );
+                            exit(0);
+                        }
+                        Err(e) => {
+                            println!("Failed to remove alias!\n{}", e);
+                            exit(1);
+                        }
+                    }
+                }
+                ProjectCommands::Rename {
+                    project_name,
+                    new_name,
+                } => {
+                    let mut database = Database::from_file(database_path.unwrap())
+                        .expect("Failed to read krabby database.");
+                    let project_name = registered_name(&database, project_name);
+                    let new_name = ProjectName::parse(new_name);
+                    match database.rename_project(&project_name, new_name.clone()) {
+                        Ok(project_file_updated) => {
+                            database.save();
+                            println!("{} is now called {}.", project_name.bold(), new_name.bold());
+                            if project_file_updated {
+                                println!("Its krabby.toml was updated as well.");
+                            }
+                            exit(0);
+                        }
+                        Err(e) => {
+                            println!("Failed to rename project!\n{}", e);
+                            exit(1);
+                        }
+                    }
+                }
+                ProjectCommands::Move {
+                    project_name,
+                    project_path,
+                    move_dir,
+                } => {
+                    let mut database = Database::from_file(database_path.unwrap())
+                        .expect("Failed to read krabby database.");
+                    let project_name = ProjectName::parse(project_name);
+                    match database.move_project(&project_name, project_path.into(), move_dir) {
+                        Ok(project_path) => {
+                            database.save();
+                            println!(
+                                "{} is now at {}.",
+                                project_name.bold(),
+                                project_path.to_string_lossy().bold()
+                            );
+                            exit(0);
+                        }
+                        Err(e) => {
+                            println!("Failed to move project!\n{}", e);
+                            exit(1);
+                        }
+                    }
+                }
+                ProjectCommands::Doctor { fix, yes } => {
+                    let database = Database::from_file(database_path.unwrap())
+                        .expect("Failed to read krabby database.");
+                    let problems = doctor::diagnose(&database);
+                    if problems.is_empty() {
+                        println!(
+                            "Checked {} projects and {}!",
+                            database.projects.len(),
+                            "everything looks fine".green().bold()
+                        );
+                        exit(0);
+                    }
+                    // With --fix, the problems that can be fixed are shown as they are asked about,
+                    // unless --yes fixes them without asking.
+                    for (project_name, problem) in &problems {
+                        if !(fix && !yes && problem.is_fixable()) {
+                            println!("{}: {}", project_name.bold(), problem);
+                        }
+                    }
+                    if fix {
+                        prune(database, problems, yes);
+                    }
+                    let fixable = problems
+                        .iter()
+                        .filter(|(_, problem)| problem.is_fixable())
+                        .count();
+                    println!(
+                        "Found {} problems, {} of which krabby can fix with {}.",
+                        problems.len(),
+                        fixable,
+                        "'kb project prune'".bold()
+                    );
+                    exit(1);
+                }
+                ProjectCommands::Prune { yes } => {
+                    let database = Database::from_file(database_path.unwrap())
+                        .expect("Failed to read krabby database.");
+                    let problems = doctor::diagnose(&database);
+                    prune(database, problems, yes);
+                }
+                ProjectCommands::Scan { path, depth, yes } => {
+                    let database = Database::from_file(database_path.unwrap())
+                        .expect("Failed to read krabby database.");
+                    let root = std::fs::canonicalize(&path).unwrap_or_else(|e| {
+                        println!("Failed to scan {}.\n{}", path.bold(), e);
+                        exit(1);
+                    });
+                    let proposal = scan::propose(&database, scan::find_projects(&root, depth));
+                    register_all(database, proposal, &IndexMap::new(), yes);
+                }
+                ProjectCommands::Export {
+                    format,
+                    relative_to,
+                } => {
+                    let database = Database::from_file(database_path.unwrap())
+                        .expect("Failed to read krabby database.");
+                    match portable::export(&database, format, &relative_to) {
+                        Ok(exported) => {
+                            print!("{}", exported);
+                            exit(0);
+                        }
+                        Err(e) => {
+                            println!("Failed to export projects!\n{}", e);
+                            exit(1);
+                        }
+                    }
+                }
+                ProjectCommands::Import {
+                    from: Some(from),
+                    file,
+                    min_score,
+                    yes,
+                    ..
+                } => {
+                    let database = Database::from_file(database_path.unwrap())
+                        .expect("Failed to read krabby database.");
+                    let imported = import::read(from, file.as_deref()).unwrap_or_else(|e| {
+                        println!("Failed to import projects!\n{}", e);
+                        exit(1);
+                    });
+                    let directories = import::directories(imported, min_score);
+                    if directories.missing > 0 {
+                        println!(
+                            "Skipping {} directories that no longer exist.",
+                            directories.missing
+                        );
+                    }
+                    let proposal = scan::propose(&database, directories.paths);
+                    register_all(database, proposal, &directories.visits, yes);
+                }
+                ProjectCommands::Import {
+                    from: None,
+                    file,
+                    strategy,
+                    ..
+                } => {
+                    let mut database = Database::from_file(database_path.unwrap())
+                        .expect("Failed to read krabby database.");
+                    // clap makes sure there is a file when there is no tool to import from.
+                    let file = file.unwrap();
+                    let contents = std::fs::read_to_string(&file).unwrap_or_else(|e| {
+                        println!("Failed to read {}.\n{}", file.to_string_lossy().bold(), e);
+                        exit(1);
+                    });
+                    let projects = portable::read(&contents).unwrap_or_else(|e| {
+                        println!("Failed to import projects!\n{}", e);
+                        exit(1);
+                    });
+                    let merge = portable::merge(&mut database, projects, strategy);
+                    database.save();
+                    for project_name in &merge.added {
+                        println!("Added {}.", project_name.bold());
+                    }
+                    for project_name in &merge.overwritten {
+                        println!("Overwrote {}.", project_name.bold());
+                    }
+                    for (project_name, new_name) in &merge.renamed {
+                        println!(
+                            "Added {} as {}, as its name is taken.",
+                            project_name.bold(),
+                            new_name.bold()
+                        );
+                    }
+                    for (project_name, reason) in &merge.skipped {
+                        println!("Skipped {}: {}.", project_name.bold(), reason);
+                    }
+                    if !merge.missing.is_empty() {
+                        let names: Vec<String> =
+                            merge.missing.iter().map(ProjectName::to_string).collect();
+                        println!(
+                            "Not on this machine yet: {}. {} will point them out until they are.",
+                            names.join(", ").bold(),
+                            "'kb project doctor'".bold()
+                        );
+                    }
+                    exit(0);
+                }
+                ProjectCommands::Set {
+                    project_name,
+                    key,
+                    value,
+                } => {
+                    let mut database = Database::from_file(database_path.unwrap())
+                        .expect("Failed to read krabby database.");
+                    let project_name = ProjectName::parse(project_name);
+                    match database.set_project_field(&project_name, &key, value.clone()) {
+                        Ok(_) => {
+                            database.save();
+                            match value {
+                                Some(value) => println!(
+                                    "{} of {} is now {}.",
+                                    key.bold(),
+                                    project_name.bold(),
+                                    value.bold()
+                                ),
+                                None => println!(
+                                    "{} of {} was {}.",
+                                    key.bold(),
+                                    project_name.bold(),
+                                    "unset".red().bold()
+                                ),
+                            }
+                            exit(0);
+                        }
+                        Err(e) => {
+                            println!("Failed to set {}.\n{}", key.bold(), e);
+                            exit(1);
+                        }
+                    }
+                }
                 ProjectCommands::Init { project_name } => {
                     let new_project_name = project_name
                         .or_else(|| {
@@ -131,42 +764,38 @@ fn main() -> Result<(), Error> {
                             Some(directory.to_string_lossy().to_string())
                         })
                         .unwrap();
-                    let project_path =
-                        std::env::current_dir().expect("Failed to read current directory");
+                    let project_file_path = project_file_path.unwrap();
                     let project_name = ProjectName::parse(new_project_name);
-                    let project = project::Project::new(project_name.clone(), Some(project_path));
-                    project.save();
+                    // A project file that is already there, like one that came with the repository, is kept.
+                    if !project_file_path.exists() {
+                        let project = project::Project::new(
+                            project_name.clone(),
+                            Some(project_file_path.clone()),
+                        );
+                        project.save();
+                    }
 
                     let mut database = Database::from_file(database_path.unwrap())
                         .expect("Failed to read krabby database.");
                     database
-                        .add_project(
-                            project_name,
-                            std::env::current_dir().expect("Failed to get current directory"),
-                        )
+                        .add_project(project_name.clone(), project_dir(&project_file_path))
                         .expect("Failed to add project file.");
                     database.save();
-                    exit(0);
+                    database.set_config(config);
+                    after_register(&database, &project_name);
                 }
-                ProjectCommands::Cd { project_name } => {
-                    let project_name = project::ProjectName::parse(project_name);
+                ProjectCommands::Cd {
+                    project_name,
+                    within,
+                } => {
                     let database = Database::from_file(database_path.unwrap())
                         .expect("Failed to read krabby database.");
-                    match database.go_to_project(&project_name) {
-                        Ok(_) => {
-                            println!("echo \"You're good to go!\"");
-                            exit(0);
-                        }
-                        Err(e) => {
-                            println!("{}", e);
-                            exit(1);
-                        }
-                    }
+                    jump(database, config, &project_name, within);
                 }
             }
         }
         Commands::Hook(hook) => {
-            let hook_cmd = hook.command.as_ref().unwrap();
+            let hook_cmd = hook.command.unwrap_or(HookCommands::Show);
 
             match hook_cmd {
                 HookCommands::Set { hook } => {
@@ -182,28 +811,154 @@ fn main() -> Result<(), Error> {
                         )),
                     };
 
-                    let mut project = project::Project::from_file(project_file_path.unwrap())
+                    let project = project::Project::from_file(project_file_path.unwrap())
                         .expect("Failed to read project_file");
-                    match project.set_hook(hook) {
-                        Ok(Some(cmd)) => {
-                            project.save();
-                            println!(
-                                "The hook was set {}!\n{} {}",
-                                "successfully".green().bold(),
-                                "[Hook]:".green().bold(),
-                                cmd.bold()
-                            );
+                    update_hook(project, hook);
+                }
+                HookCommands::Show => {
+                    let project = project::Project::from_file(project_file_path.unwrap())
+                        .expect("Failed to read project_file");
+                    match project.hook() {
+                        Some(_) => {
+                            println!("{}", "[Hook]:".green().bold());
+                            print_hook_steps(&project);
+                        }
+                        None => println!("There is {}.", "no hook set".red().bold()),
+                    }
+                    exit(0);
+                }
+                HookCommands::Run { hook } => {
+                    let project_file_path = project_file_path.unwrap();
+                    let project = project::Project::from_file(project_file_path.clone())
+                        .expect("Failed to read project_file");
+                    let hook = match hook {
+                        HookKind::Hook => project.hook(),
+                        HookKind::OnLeave => project.on_leave(),
+                        HookKind::OnFirstEnter => project.on_first_enter(),
+                        HookKind::OnInit => project.on_init(),
+                    };
+                    let Some(hook) = hook else {
+                        println!("There is {}.", "no hook set".red().bold());
+                        exit(1);
+                    };
+                    match hook.execute(
+                        project.scripts(),
+                        &project.log(),
+                        &project_dir(&project_file_path),
+                    ) {
+                        Ok(_) => exit(0),
+                        Err(e) => {
+                            println!("{}", e);
+                            exit(1);
+                        }
+                    }
+                }
+                HookCommands::Clear => {
+                    let project = project::Project::from_file(project_file_path.unwrap())
+                        .expect("Failed to read project_file");
+                    update_hook(project, None);
+                }
+                HookCommands::Add { entry, run } => {
+                    let project = project::Project::from_file(project_file_path.unwrap())
+                        .expect("Failed to read project_file");
+                    let entry = hook::HookEntry::from_input(&entry, run);
+                    let hook = Some(match project.hook().clone() {
+                        Some(hook) => hook.add_entry(entry),
+                        None => ProjectHook::from_entry(entry),
+                    });
+                    if let Err(e) = project.validate_hook(&hook) {
+                        println!(
+                            "Failed to set hook.\n{}\nAdd it with {} if it is an inline command.",
+                            e,
+                            "--run".bold()
+                        );
+                        exit(1);
+                    }
+                    update_hook(project, hook);
+                }
+                HookCommands::Remove { entry } => {
+                    let project = project::Project::from_file(project_file_path.unwrap())
+                        .expect("Failed to read project_file");
+                    let Some(hook) = project.hook().clone() else {
+                        println!("There is {}.", "no hook set".red().bold());
+                        exit(1);
+                    };
+                    match hook.remove_entry(&entry) {
+                        Ok(hook) => update_hook(project, hook),
+                        Err(e) => {
+                            println!("Failed to remove hook entry.\n{}", e);
+                            exit(1);
+                        }
+                    }
+                }
+                HookCommands::Edit => {
+                    let project_file_path = project_file_path.unwrap();
+                    let editor = std::env::var("VISUAL")
+                        .or_else(|_| std::env::var("EDITOR"))
+                        .unwrap_or_else(|_| "vi".to_string());
+                    let status = std::process::Command::new("sh")
+                        .arg("-c")
+                        .arg(format!(
+                            "{} {}",
+                            editor,
+                            shell::quote(&project_file_path.to_string_lossy())
+                        ))
+                        .status()
+                        .expect("Failed to open the editor.");
+                    if !status.success() {
+                        println!("The editor exited with {}.", status.red().bold());
+                        exit(1);
+                    }
+                    match project::Project::from_file(project_file_path) {
+                        Ok(project) => {
+                            if project.hook().is_some() {
+                                println!("{}", "[Hook]:".green().bold());
+                                print_hook_steps(&project);
+                            }
                             exit(0);
                         }
-                        Ok(None) => {
-                            project.save();
-                            // println!("There was {}.", "no hook found".red().bold());
-                            println!("The hook was {}", "removed.".red().bold());
+                        Err(e) => {
+                            println!("The project file is {}.\n{}", "invalid".red().bold(), e);
+                            exit(1);
+                        }
+                    }
+                }
+                HookCommands::Allow => {
+                    let project_file_path = project_file_path.unwrap();
+                    let project = project::Project::from_file(project_file_path.clone())
+                        .expect("Failed to read project_file");
+                    let mut database = Database::from_file(database_path.unwrap())
+                        .expect("Failed to read krabby database.");
+                    if let Err(e) =
+                        database.allow_project(project_dir(&project_file_path), &project)
+                    {
+                        println!("Failed to allow hooks.\n{}", e);
+                        exit(1);
+                    }
+                    database.save();
+                    println!(
+                        "The hooks of {} were {}!",
+                        project.name.bold(),
+                        "allowed".green().bold()
+                    );
+                    for cmd in project.resolved_commands().unwrap_or_default() {
+                        println!("\t- {}", cmd.bold());
+                    }
+                    exit(0);
+                }
+                HookCommands::Deny => {
+                    let project_file_path = project_file_path.unwrap();
+                    let mut database = Database::from_file(database_path.unwrap())
+                        .expect("Failed to read krabby database.");
+                    match database.deny_project(&project_dir(&project_file_path)) {
+                        Ok(_) => {
+                            database.save();
+                            println!("The hooks were {}.", "denied".red().bold());
                             exit(0);
                         }
                         Err(e) => {
-                            println!("Failed to set hook.\n{}", e);
-                            exit(1)
+                            println!("Failed to deny hooks.\n{}", e);
+                            exit(1);
                         }
                     }
                 }
@@ -281,13 +1036,49 @@ fn main() -> Result<(), Error> {
         Commands::Shell(shell) => {
             let shell_cmd = shell.command.as_ref().unwrap();
 
-            match shell_cmd {
-                ShellCommands::Bash | ShellCommands::Zsh => {
-                    let script = shell::Shell(Flavor::Bash).script();
-                    print!("{}", script);
-                    exit(1);
-                }
+            let flavor = match shell_cmd {
+                ShellCommands::Bash => Flavor::Bash,
+                ShellCommands::Zsh => Flavor::Zsh,
+            };
+            print!("{}", shell::Shell(flavor).script());
+            exit(1);
+        }
+        Commands::Chpwd => {
+            // Called by the shell integration whenever the working directory changes.
+            let mut database = Database::from_file(database_path.unwrap())
+                .expect("Failed to read krabby database.");
+            database.set_config(config);
+            let cwd = std::env::current_dir().expect("Failed to read current directory");
+            let cmd = database.change_directory(&cwd, active_project().as_deref());
+            // Nothing changes on the database unless the shell got in or out of a project.
+            if !cmd.is_empty() {
+                database.save();
+            }
+            print!("{}", cmd);
+            exit(0);
+        }
+        Commands::Complete { word } => {
+            let database = Database::from_file(database_path.unwrap())
+                .expect("Failed to read krabby database.");
+            for completion in database.complete(&word) {
+                println!("{}", completion);
             }
+            exit(0);
+        }
+        Commands::FirstEntered { project_name, hash } => {
+            let mut database = Database::from_file(database_path.unwrap())
+                .expect("Failed to read krabby database.");
+            database.record_first_enter(&ProjectName::parse(project_name), hash);
+            database.save();
+            exit(0);
+        }
+        Commands::Report => {
+            let reports =
+                hook::take_reports(&hook::log_dir()).expect("Failed to read background hook logs");
+            for report in reports {
+                Message::BackgroundHookFinished(report).println();
+            }
+            exit(0);
         }
     }
 }
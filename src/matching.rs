@@ -0,0 +1,60 @@
+/// Tells whether every character of `query` shows up in `name`, in the same order.
+pub fn is_subsequence(query: &str, name: &str) -> bool {
+    let mut name = name.chars();
+    query.chars().all(|c| name.any(|n| n == c))
+}
+
+/// Number of single character insertions, deletions or substitutions turning `a` into `b`.
+pub fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// The `names` close enough to `query` to be what the user meant, closest first.
+pub fn closest<'a>(query: &str, names: impl Iterator<Item = &'a str>) -> Vec<&'a str> {
+    let query = query.to_lowercase();
+    let threshold = (query.chars().count() / 3).max(2);
+    let mut close: Vec<(usize, &str)> = names
+        .map(|name| (distance(&query, &name.to_lowercase()), name))
+        .filter(|(distance, _)| *distance <= threshold)
+        .collect();
+    close.sort_by_key(|(distance, _)| *distance);
+    close.into_iter().take(3).map(|(_, name)| name).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn subsequences_keep_their_order() {
+        assert!(is_subsequence("kby", "krabby"));
+        assert!(is_subsequence("", "krabby"));
+        assert!(!is_subsequence("ybk", "krabby"));
+        assert!(!is_subsequence("krabbyy", "krabby"));
+    }
+
+    #[test]
+    fn distance_counts_edits() {
+        assert_eq!(distance("krabby", "krabby"), 0);
+        assert_eq!(distance("krabyb", "krabby"), 2);
+        assert_eq!(distance("crabby", "krabby"), 1);
+        assert_eq!(distance("", "api"), 3);
+    }
+
+    #[test]
+    fn closest_names_come_first() {
+        let names = ["krabby", "crab", "api", "backend-api"];
+        assert_eq!(closest("krabi", names.into_iter()), vec!["krabby", "crab"]);
+        assert!(closest("zzzzzz", names.into_iter()).is_empty());
+    }
+}
@@ -1,16 +1,26 @@
-use std::fmt::{self, Display};
+use std::{
+    fmt::{self, Display},
+    path::PathBuf,
+};
 
 use owo_colors::OwoColorize;
 
-use crate::project::ProjectName;
+use crate::{hook::BackgroundReport, project::ProjectName};
 
 pub enum Message {
     RegisterProject,
     RegisterScript,
-    ProjectNotFound(ProjectName),
+    /// The name the user typed, and the registered names closest to it.
+    ProjectNotFound(String, Vec<ProjectName>),
     ProjectFileNotFound(ProjectName, String),
     RegisterProjectSuccess(ProjectName),
     RegisterProjectFail(ProjectName, anyhow::Error),
+    HookNotAllowed(ProjectName),
+    HookChanged(ProjectName, String),
+    HookBroken(ProjectName, anyhow::Error),
+    InitNotAllowed(ProjectName),
+    ProjectFileInvalid(PathBuf, anyhow::Error),
+    BackgroundHookFinished(BackgroundReport),
 }
 
 impl Message {
@@ -36,13 +46,25 @@ impl Display for Message {
                     "'kb script add SCRIPT_NAME SCRIPT_COMMAND'".bold()
                 )
             }
-            Self::ProjectNotFound(project_name) => {
+            Self::ProjectNotFound(project_name, suggestions) if suggestions.is_empty() => {
                 format!(
                     "Project {} was {} on database! Are you sure you registered it?",
                     project_name.bold(),
                     "not found".red().bold(),
                 )
             }
+            Self::ProjectNotFound(project_name, suggestions) => {
+                let suggestions: Vec<String> = suggestions
+                    .iter()
+                    .map(|suggestion| suggestion.bold().to_string())
+                    .collect();
+                format!(
+                    "Project {} was {} on database! Did you mean {}?",
+                    project_name.bold(),
+                    "not found".red().bold(),
+                    suggestions.join(" or ")
+                )
+            }
             Self::ProjectFileNotFound(project_name, path) => {
                 format!("I dont know how to tell you this, but there was a problem with the registry.\nApparently the {} directory {} or {} {}.", project_name.bold(), "is missing".red().bold(), path, "is not a directory".red().bold())
             }
@@ -57,6 +79,67 @@ impl Display for Message {
                     error
                 )
             }
+            Self::HookNotAllowed(project_name) => {
+                format!(
+                    "The hooks of {} were {}, so they did not run.\nCheck its krabby.toml and approve them with {} from the project directory.",
+                    project_name.bold(),
+                    "not allowed yet".red().bold(),
+                    "'kb hook allow'".bold()
+                )
+            }
+            Self::HookChanged(project_name, diff) => {
+                format!(
+                    "The hooks of {} {} since you allowed them, so they did not run:\n{}\nApprove them again with {} from the project directory.",
+                    project_name.bold(),
+                    "changed".red().bold(),
+                    diff,
+                    "'kb hook allow'".bold()
+                )
+            }
+            Self::HookBroken(project_name, error) => {
+                format!(
+                    "The hooks of {} {}, so they did not run:\n{}",
+                    project_name.bold(),
+                    "could not be resolved".red().bold(),
+                    error
+                )
+            }
+            Self::InitNotAllowed(project_name) => {
+                format!(
+                    "The {} hook of {} was {}, so it did not run.\nCheck its krabby.toml, approve it with {} and run it with {} from the project directory.",
+                    "on_init".bold(),
+                    project_name.bold(),
+                    "not allowed yet".red().bold(),
+                    "'kb hook allow'".bold(),
+                    "'kb hook run on_init'".bold()
+                )
+            }
+            Self::ProjectFileInvalid(path, error) => {
+                format!(
+                    "{} {}, so its hooks did not run:\n{}",
+                    path.to_string_lossy().bold(),
+                    "could not be read".red().bold(),
+                    error
+                )
+            }
+            Self::BackgroundHookFinished(report) if report.success() => {
+                format!(
+                    "Background hook entry {} of {} {}.",
+                    report.entry.bold(),
+                    report.project.bold(),
+                    "finished successfully".green().bold()
+                )
+            }
+            Self::BackgroundHookFinished(report) => {
+                format!(
+                    "Background hook entry {} of {} {} {}.\nCheck its output at {}",
+                    report.entry.bold(),
+                    report.project.bold(),
+                    "failed with exit code".red().bold(),
+                    report.code.red().bold(),
+                    report.log.to_string_lossy().bold()
+                )
+            }
         };
         write!(f, "{}", message)
     }
@@ -0,0 +1,343 @@
+use crate::{
+    commands::{ExportFormat, MergeStrategy},
+    database::{Database, ProjectEntry},
+    project::ProjectName,
+};
+use anyhow::{anyhow, Error};
+use indexmap::IndexMap;
+use owo_colors::OwoColorize;
+use std::path::{Path, PathBuf};
+
+/// Writes the projects of `database` so another machine can import them.
+/// Paths under any of the `bases`, `~` or the name of an environment variable,
+/// are written relative to it, as in `~/code/api` or `$CODE/api`.
+pub fn export(
+    database: &Database,
+    format: ExportFormat,
+    bases: &[String],
+) -> Result<String, Error> {
+    let bases = bases
+        .iter()
+        .map(|base| resolve_base(base))
+        .collect::<Result<Vec<(String, PathBuf)>, Error>>()?;
+    // Only the projects travel: approvals and first entries are for this machine alone.
+    let mut exported = Database::new(None);
+    for (project_name, entry) in &database.projects {
+        let entry = ProjectEntry {
+            path: relativize(&entry.path, &bases),
+            first_entered: None,
+            ..entry.clone()
+        };
+        exported.projects.insert(project_name.clone(), entry);
+    }
+    Ok(match format {
+        ExportFormat::Toml => exported.to_string(),
+        ExportFormat::Json => serde_json::to_string_pretty(&exported)? + "\n",
+    })
+}
+
+/// Reads projects written by `export`, in either format, with their paths expanded for this machine.
+pub fn read(contents: &str) -> Result<IndexMap<ProjectName, ProjectEntry>, Error> {
+    let database: Database = match contents.trim_start().starts_with('{') {
+        true => serde_json::from_str(contents)?,
+        false => toml::from_str(contents)?,
+    };
+    database
+        .projects
+        .into_iter()
+        .map(|(project_name, entry)| {
+            let path = expand(&entry.path)?;
+            // Projects that are not on this machine yet are kept as they are written.
+            let path = std::fs::canonicalize(&path).unwrap_or(path);
+            Ok((project_name, ProjectEntry { path, ..entry }))
+        })
+        .collect()
+}
+
+/// The placeholder and directory `base` stands for: `~` for the home directory,
+/// anything else for the environment variable named so.
+fn resolve_base(base: &str) -> Result<(String, PathBuf), Error> {
+    match base {
+        "~" => Ok((
+            "~".to_string(),
+            dirs::home_dir().ok_or_else(|| anyhow!("Failed to find the home directory."))?,
+        )),
+        name => match std::env::var(name) {
+            Ok(value) if !value.is_empty() => Ok((format!("${}", name), PathBuf::from(value))),
+            _ => Err(anyhow!("{} is not set.", name.bold())),
+        },
+    }
+}
+
+/// Writes `path` relative to the longest of the `bases` it is under, if any.
+fn relativize(path: &Path, bases: &[(String, PathBuf)]) -> PathBuf {
+    bases
+        .iter()
+        .filter(|(_, base)| path.starts_with(base))
+        .max_by_key(|(_, base)| base.components().count())
+        .map(|(placeholder, base)| {
+            PathBuf::from(placeholder).join(path.strip_prefix(base).unwrap())
+        })
+        .unwrap_or_else(|| path.to_path_buf())
+}
+
+/// Turns a path written by `export` into one for this machine,
+/// expanding a leading `~`, `$VAR` or `${VAR}`.
+pub fn expand(path: &Path) -> Result<PathBuf, Error> {
+    let written = path.to_string_lossy();
+    let (base, rest) = match written.split_once('/') {
+        Some((base, rest)) => (base, rest),
+        None => (written.as_ref(), ""),
+    };
+    let base = match base {
+        "~" => dirs::home_dir().ok_or_else(|| anyhow!("Failed to find the home directory."))?,
+        base if base.starts_with('$') => {
+            let name = base
+                .trim_start_matches('$')
+                .trim_start_matches('{')
+                .trim_end_matches('}');
+            match std::env::var(name) {
+                Ok(value) if !value.is_empty() => PathBuf::from(value),
+                _ => {
+                    return Err(anyhow!(
+                        "{} is not set, so {} cannot be found.",
+                        name.bold(),
+                        written.bold()
+                    ))
+                }
+            }
+        }
+        _ => return Ok(path.to_path_buf()),
+    };
+    Ok(match rest {
+        "" => base,
+        rest => base.join(rest),
+    })
+}
+
+/// What merging imported projects into the database did.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Merge {
+    pub added: Vec<ProjectName>,
+    pub overwritten: Vec<ProjectName>,
+    /// Projects registered under another name, as their own was taken.
+    pub renamed: Vec<(ProjectName, ProjectName)>,
+    /// Projects left out, and why.
+    pub skipped: Vec<(ProjectName, String)>,
+    /// Projects registered at directories that are not on this machine yet.
+    pub missing: Vec<ProjectName>,
+}
+
+/// Adds the imported `projects` to `database`, dealing with names that are taken as `strategy` says.
+/// Projects at a directory that is registered already are left out,
+/// unless they overwrite the very project registered there.
+pub fn merge(
+    database: &mut Database,
+    projects: IndexMap<ProjectName, ProjectEntry>,
+    strategy: MergeStrategy,
+) -> Merge {
+    let mut merge = Merge::default();
+    for (project_name, mut entry) in projects {
+        let taken = database.canonical_name(&project_name);
+        let registered_at = database
+            .projects
+            .iter()
+            .find(|(_, registered)| registered.path == entry.path)
+            .map(|(registered_name, _)| registered_name.clone());
+        if let Some(registered_name) = registered_at.filter(|registered_name| {
+            *registered_name != project_name || strategy != MergeStrategy::Overwrite
+        }) {
+            merge.skipped.push((
+                project_name,
+                format!(
+                    "its directory is registered as {} already",
+                    registered_name.bold()
+                ),
+            ));
+            continue;
+        }
+        let name = match (taken, strategy) {
+            (None, _) => project_name.clone(),
+            (Some(_), MergeStrategy::Skip) => {
+                merge
+                    .skipped
+                    .push((project_name, "its name is taken".to_string()));
+                continue;
+            }
+            // Aliases are only names, so they make way for the project.
+            (Some(taken), MergeStrategy::Overwrite) if taken != project_name => {
+                database.unalias_project(&project_name).unwrap();
+                project_name.clone()
+            }
+            (Some(_), MergeStrategy::Overwrite) => project_name.clone(),
+            (Some(_), MergeStrategy::Rename) => free_name(database, &project_name),
+        };
+        if !entry.path.is_dir() {
+            merge.missing.push(name.clone());
+        }
+        // Aliases that are taken are dropped rather than fought over.
+        entry.aliases.retain(|alias| {
+            *alias != name
+                && database
+                    .canonical_name(alias)
+                    .is_none_or(|owner| owner == name)
+        });
+        match database.projects.insert(name.clone(), entry) {
+            Some(_) => merge.overwritten.push(name),
+            None if name != project_name => merge.renamed.push((project_name, name)),
+            None => merge.added.push(name),
+        }
+    }
+    merge
+}
+
+/// The first of `project_name-2`, `project_name-3` and so on nothing is called yet.
+fn free_name(database: &Database, project_name: &ProjectName) -> ProjectName {
+    (2..)
+        .map(|n| project_name.with_suffix(&format!("-{}", n)))
+        .find(|name| database.canonical_name(name).is_none())
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn name(name: &str) -> ProjectName {
+        ProjectName::parse(name.into())
+    }
+
+    #[test]
+    fn paths_are_written_relative_to_the_longest_base() {
+        let bases = vec![
+            ("~".to_string(), PathBuf::from("/home/me")),
+            ("$CODE".to_string(), PathBuf::from("/home/me/code")),
+        ];
+        assert_eq!(
+            relativize(Path::new("/home/me/code/api"), &bases),
+            PathBuf::from("$CODE/api")
+        );
+        assert_eq!(
+            relativize(Path::new("/home/me/notes"), &bases),
+            PathBuf::from("~/notes")
+        );
+        assert_eq!(
+            relativize(Path::new("/home/me"), &bases),
+            PathBuf::from("~")
+        );
+        assert_eq!(
+            relativize(Path::new("/srv/web"), &bases),
+            PathBuf::from("/srv/web")
+        );
+    }
+
+    #[test]
+    fn paths_are_expanded_for_this_machine() {
+        let home = dirs::home_dir().unwrap();
+        std::env::set_var("KRABBY_TEST_CODE", "/srv/code");
+        assert_eq!(expand(Path::new("~/notes")).unwrap(), home.join("notes"));
+        assert_eq!(expand(Path::new("~")).unwrap(), home);
+        assert_eq!(
+            expand(Path::new("$KRABBY_TEST_CODE/api")).unwrap(),
+            PathBuf::from("/srv/code/api")
+        );
+        assert_eq!(
+            expand(Path::new("${KRABBY_TEST_CODE}/api")).unwrap(),
+            PathBuf::from("/srv/code/api")
+        );
+        assert_eq!(
+            expand(Path::new("/srv/web")).unwrap(),
+            PathBuf::from("/srv/web")
+        );
+        assert!(expand(Path::new("$KRABBY_TEST_UNSET/api")).is_err());
+    }
+
+    #[test]
+    fn exports_can_be_read_back_in_either_format() {
+        let home = dirs::home_dir().unwrap();
+        let mut database = Database::new(None);
+        let mut entry = ProjectEntry::new(home.join("krabby-export-test"));
+        entry.description = Some("Not on this machine".into());
+        entry.aliases = vec![name("kb")];
+        database.projects.insert(name("krabby"), entry.clone());
+        database
+            .projects
+            .insert(name("web"), ProjectEntry::new("/srv/krabby-web".into()));
+        let expected = database.projects.clone();
+        // The hook ran on this machine, not on the one importing the projects.
+        database.projects[1].first_entered = Some("hash".into());
+
+        for format in [ExportFormat::Toml, ExportFormat::Json] {
+            let exported = export(&database, format, &["~".to_string()]).unwrap();
+            assert!(exported.contains("~/krabby-export-test"));
+            assert_eq!(read(&exported).unwrap(), expected);
+        }
+        assert!(export(&database, ExportFormat::Toml, &["KRABBY_TEST_UNSET".into()]).is_err());
+    }
+
+    fn imported() -> IndexMap<ProjectName, ProjectEntry> {
+        IndexMap::from([
+            (name("api"), ProjectEntry::new("/srv/api".into())),
+            (name("web"), ProjectEntry::new("/tmp".into())),
+            (name("cli"), ProjectEntry::new("/srv/cli".into())),
+        ])
+    }
+
+    fn registered() -> Database {
+        let mut database = Database::from_string(
+            r#"
+            [projects]
+            api = "/srv/old-api"
+            tmp = "/tmp"
+            "#,
+        );
+        database.alias_project(&name("api"), name("cli")).unwrap();
+        database
+    }
+
+    #[test]
+    fn merging_skips_taken_names() {
+        let mut database = registered();
+        let merge = merge(&mut database, imported(), MergeStrategy::Skip);
+        assert_eq!(merge.added, Vec::<ProjectName>::new());
+        let skipped: Vec<ProjectName> = merge.skipped.into_iter().map(|(name, _)| name).collect();
+        assert_eq!(skipped, vec![name("api"), name("web"), name("cli")]);
+        assert_eq!(
+            database.get_project_path(&name("api")),
+            Some(&PathBuf::from("/srv/old-api"))
+        );
+    }
+
+    #[test]
+    fn merging_can_overwrite_taken_names() {
+        let mut database = registered();
+        let merge = merge(&mut database, imported(), MergeStrategy::Overwrite);
+        assert_eq!(merge.overwritten, vec![name("api")]);
+        assert_eq!(merge.added, vec![name("cli")]);
+        assert_eq!(merge.missing, vec![name("api"), name("cli")]);
+        assert_eq!(
+            database.get_project_path(&name("api")),
+            Some(&PathBuf::from("/srv/api"))
+        );
+        // The alias made way for the project.
+        assert_eq!(database.alias_owner(&name("cli")), None);
+    }
+
+    #[test]
+    fn merging_can_rename_taken_names() {
+        let mut database = registered();
+        let merge = merge(&mut database, imported(), MergeStrategy::Rename);
+        assert_eq!(
+            merge.renamed,
+            vec![(name("api"), name("api-2")), (name("cli"), name("cli-2"))]
+        );
+        assert_eq!(
+            free_name(&database, &name("a-really-long-name")),
+            name("a-really-long-name-2")
+        );
+        assert_eq!(
+            free_name(&database, &name("a-really-long-name-x")),
+            name("a-really-long-name-2")
+        );
+    }
+}
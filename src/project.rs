@@ -1,6 +1,8 @@
 use crate::{
-    hook::ProjectHook,
+    env,
+    hook::{HookLog, ProjectHook},
     script::{Command, Script, ScriptName},
+    shell,
 };
 use anyhow::{anyhow, Error};
 use indexmap::IndexMap;
@@ -20,8 +22,57 @@ pub struct Project {
     #[serde(skip)]
     pub path: Option<PathBuf>,
     pub hook: Option<ProjectHook>,
+    /// Runs whenever the shell leaves the project directory.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_leave: Option<ProjectHook>,
+    /// Runs the first time the project is entered on this machine, and again whenever it changes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_first_enter: Option<ProjectHook>,
+    /// Runs once the project is registered with `kb project add` or `kb project init`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_init: Option<ProjectHook>,
+    /// Variables exported while the shell is inside the project.
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub env: IndexMap<String, String>,
     #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
     pub scripts: IndexMap<ScriptName, Script>,
+    /// Directories inside the project the user can jump to by name, as in `kb api:web`.
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub places: IndexMap<String, Place>,
+}
+
+/// A directory inside the project, either as a plain path relative to the project
+/// or along with a hook that runs from it whenever the user jumps there:
+///
+/// ```toml
+/// [places]
+/// web = "apps/web"
+/// infra = { path = "deploy/terraform", hook = "terraform init" }
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum Place {
+    Path(PathBuf),
+    Detailed {
+        path: PathBuf,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        hook: Option<ProjectHook>,
+    },
+}
+
+impl Place {
+    pub fn path(&self) -> &PathBuf {
+        match self {
+            Self::Path(path) | Self::Detailed { path, .. } => path,
+        }
+    }
+
+    pub fn hook(&self) -> &Option<ProjectHook> {
+        match self {
+            Self::Path(_) => &None,
+            Self::Detailed { hook, .. } => hook,
+        }
+    }
 }
 
 impl Project {
@@ -31,6 +82,11 @@ impl Project {
             path,
             scripts: IndexMap::new(),
             hook: None,
+            on_leave: None,
+            on_first_enter: None,
+            on_init: None,
+            env: IndexMap::new(),
+            places: IndexMap::new(),
         }
     }
 
@@ -73,45 +129,130 @@ impl Project {
         match self.validate_hook(&hook) {
             Ok(_) => {
                 self.hook = hook;
-                match self.get_hook_cmd() {
-                    Some(cmd) => Ok(Some(cmd)),
-                    None => Ok(None),
-                }
+                self.get_hook_cmd()
             }
             Err(e) => Err(e),
         }
     }
 
-    pub fn get_hook_cmd(&self) -> Option<String> {
-        match self.validate_hook(self.hook()) {
-            Ok(_) => match self.hook() {
-                Some(ProjectHook::Simple(cmd)) => Some(cmd.to_string()),
-                Some(ProjectHook::ScriptArray(hooks)) => {
-                    let hooks_cmd = hooks
-                        .iter()
-                        .map(|s| {
-                            self.get_script(s)
-                                .unwrap_or_else(|| {
-                                    panic!("Hook {} does not match any script", s.bold());
-                                })
-                                .to_string()
-                        })
-                        .collect::<Vec<String>>()
-                        .join("; ");
-                    Some(hooks_cmd)
-                }
-                None => None,
-            },
-            Err(e) => {
-                panic!("Failed to get hook command.\n{}", e);
+    pub fn get_hook_cmd(&self) -> Result<Option<String>, Error> {
+        self.render_hook(self.hook())
+    }
+
+    pub fn on_leave(&self) -> &Option<ProjectHook> {
+        &self.on_leave
+    }
+
+    /// Defines the hook that runs when leaving the project, same as `set_hook`.
+    pub fn set_on_leave(&mut self, hook: Option<ProjectHook>) -> Result<Option<String>, Error> {
+        self.validate_hook(&hook)?;
+        self.on_leave = hook;
+        self.get_on_leave_cmd()
+    }
+
+    pub fn get_on_leave_cmd(&self) -> Result<Option<String>, Error> {
+        self.render_hook(self.on_leave())
+    }
+
+    pub fn on_first_enter(&self) -> &Option<ProjectHook> {
+        &self.on_first_enter
+    }
+
+    pub fn get_on_first_enter_cmd(&self) -> Result<Option<String>, Error> {
+        self.render_hook(self.on_first_enter())
+    }
+
+    pub fn on_init(&self) -> &Option<ProjectHook> {
+        &self.on_init
+    }
+
+    pub fn get_on_init_cmd(&self) -> Result<Option<String>, Error> {
+        self.render_hook(self.on_init())
+    }
+
+    pub fn get_place(&self, place: &str) -> Option<&Place> {
+        self.places.get(place)
+    }
+
+    /// The hook of `place`, if it has one.
+    pub fn get_place_hook_cmd(&self, place: &str) -> Result<Option<String>, Error> {
+        match self.get_place(place) {
+            Some(place) => self.render_hook(place.hook()),
+            None => Err(anyhow!("There is no place {}.", place.bold())),
+        }
+    }
+
+    /// Checks that places can be reached by name and stay inside the project.
+    fn validate_places(&self) -> Result<(), Error> {
+        for (name, place) in &self.places {
+            if name.is_empty() || name.contains(['/', ':']) {
+                return Err(anyhow!(
+                    "{} is not a valid place name, as it cannot be empty or have a '/' or ':'.",
+                    name.bold()
+                ));
+            }
+            if place.path().is_absolute() {
+                return Err(anyhow!(
+                    "The path of place {} must be relative to the project.",
+                    name.bold()
+                ));
             }
+            self.validate_hook(place.hook())?;
         }
+        Ok(())
+    }
+
+    /// Where the background entries of the project hooks write to.
+    pub fn log(&self) -> HookLog {
+        HookLog::new(&self.name.to_string())
+    }
+
+    /// Everything krabby runs or exports on behalf of the project, so the user can approve it.
+    /// Hooks are listed entry by entry as the user wrote them, so approvals only change with them.
+    pub fn resolved_commands(&self) -> Result<Vec<String>, Error> {
+        let mut commands = Vec::new();
+        commands.extend(self.hook_summary("hook", self.hook())?);
+        commands.extend(self.hook_summary("on_leave", self.on_leave())?);
+        commands.extend(self.hook_summary("on_first_enter", self.on_first_enter())?);
+        commands.extend(self.hook_summary("on_init", self.on_init())?);
+        for (name, value) in &self.env {
+            commands.push(format!("env: {}={}", name, shell::quote(value)));
+        }
+        for (name, place) in &self.places {
+            let label = format!("places.{} ({})", name, place.path().to_string_lossy());
+            commands.extend(self.hook_summary(&label, place.hook())?);
+        }
+        Ok(commands)
+    }
+
+    /// The lines of `hook` to approve, each one labeled with where the hook comes from.
+    fn hook_summary(&self, label: &str, hook: &Option<ProjectHook>) -> Result<Vec<String>, Error> {
+        self.validate_hook(hook)?;
+        Ok(match hook {
+            Some(hook) => hook
+                .summary(self.scripts())?
+                .into_iter()
+                .map(|line| format!("{}: {}", label, line))
+                .collect(),
+            None => Vec::new(),
+        })
+    }
+
+    fn render_hook(&self, hook: &Option<ProjectHook>) -> Result<Option<String>, Error> {
+        self.validate_hook(hook)?;
+        hook.as_ref()
+            .map(|hook| hook.render(self.scripts(), &self.log()))
+            .transpose()
     }
 
     pub fn validate_hook(&self, hook: &Option<ProjectHook>) -> Result<(), Error> {
         match hook {
-            Some(ProjectHook::ScriptArray(hooks)) => {
-                for h in hooks {
+            Some(
+                hook @ (ProjectHook::ScriptArray(_)
+                | ProjectHook::Mixed(_)
+                | ProjectHook::Detailed(_)),
+            ) => {
+                for h in hook.script_names() {
                     if !self
                         .scripts
                         .contains_key(&ScriptName::parse(h.to_string().clone()))
@@ -188,7 +329,7 @@ impl Project {
             )?;
         }
         let mut project = Self::from_str(&contents)
-            .unwrap_or_else(|e| panic!("Failed to read project string.\n{}", e));
+            .map_err(|e| anyhow!("Failed to read project string.\n{}", e))?;
         project.set_path(path);
         Ok(project)
     }
@@ -203,7 +344,18 @@ impl FromStr for Project {
             let _ = p.populate_names();
             // match project.validate_hook() {}
             // p.validate_hook(p.hook())?
-            match p.validate_hook(p.hook()) {
+            if let Err(e) = p.env.keys().try_for_each(|name| env::validate_name(name)) {
+                return Err(anyhow!("Krabby failed to validate project env.\n{}", e));
+            }
+            if let Err(e) = p.validate_places() {
+                return Err(anyhow!("Krabby failed to validate project places.\n{}", e));
+            }
+            match p
+                .validate_hook(p.hook())
+                .and_then(|_| p.validate_hook(p.on_leave()))
+                .and_then(|_| p.validate_hook(p.on_first_enter()))
+                .and_then(|_| p.validate_hook(p.on_init()))
+            {
                 Ok(_) => {
                     return Ok(p);
                 }
@@ -216,12 +368,19 @@ impl FromStr for Project {
     }
 }
 
-impl ToString for Project {
-    fn to_string(&self) -> String {
-        toml::to_string(self).unwrap()
+impl Display for Project {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Going through `toml::Value` makes sure plain values are written before tables,
+        // no matter which form each hook takes.
+        let value = toml::Value::try_from(self).unwrap();
+        write!(f, "{}", toml::to_string(&value).unwrap())
     }
 }
 
+const MAX_NAME_LENGTH: usize = 20;
+/// `:` separates a project from one of its places, as in `kb api:web`.
+const FORBIDDEN_CHARACTERS: [char; 11] = ['/', ':', '(', ')', '"', '\'', '<', '>', '\\', '{', '}'];
+
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq, Hash)]
 #[serde(transparent)]
 pub struct ProjectName(String);
@@ -229,16 +388,50 @@ impl ProjectName {
     pub fn parse(s: String) -> Self {
         let is_empty = s.trim().is_empty();
 
-        let is_too_long = s.len() > 20;
+        let is_too_long = s.len() > MAX_NAME_LENGTH;
 
-        let forbidden_characters = ['/', '(', ')', '"', '\'', '<', '>', '\\', '{', '}'];
-        let contains_forbidden_characters = s.chars().any(|g| forbidden_characters.contains(&g));
+        let contains_forbidden_characters = s.chars().any(|g| FORBIDDEN_CHARACTERS.contains(&g));
 
         if is_empty || is_too_long || contains_forbidden_characters {
             panic!("{} is not a valid script name", s);
         }
         Self(s)
     }
+
+    /// Makes a valid name out of `s`, like a directory name, when there is anything left of it.
+    /// Forbidden characters and whitespace become dashes, and long names are cut short.
+    pub fn sanitize(s: &str) -> Option<Self> {
+        let replaced: String = s
+            .chars()
+            .map(|c| match c {
+                c if FORBIDDEN_CHARACTERS.contains(&c) || c.is_whitespace() => '-',
+                c => c,
+            })
+            .collect();
+        let name = truncate(replaced.trim_matches('-'), MAX_NAME_LENGTH);
+        let name = name.trim_end_matches('-');
+        match name.is_empty() {
+            true => None,
+            false => Some(Self(name.to_string())),
+        }
+    }
+
+    /// The name followed by `suffix`, cutting the name short so both fit.
+    pub fn with_suffix(&self, suffix: &str) -> Self {
+        let name = truncate(&self.0, MAX_NAME_LENGTH.saturating_sub(suffix.len()));
+        Self::parse(format!("{}{}", name, suffix))
+    }
+}
+
+/// The longest start of `s` that takes at most `max_len` bytes, without splitting a character.
+fn truncate(s: &str, max_len: usize) -> &str {
+    let end = s
+        .char_indices()
+        .map(|(i, c)| i + c.len_utf8())
+        .take_while(|&end| end <= max_len)
+        .last()
+        .unwrap_or(0);
+    &s[..end]
 }
 
 impl Display for ProjectName {
@@ -251,7 +444,7 @@ impl Display for ProjectName {
 mod test {
     use std::panic;
 
-    use crate::script::Command;
+    use crate::{hook::HookEntry, script::Command};
 
     use super::*;
 
@@ -317,6 +510,203 @@ mod test {
         assert_eq!(project, project_from_str)
     }
 
+    #[test]
+    fn project_on_leave_hook_parsed_successfully() {
+        let mut project = Project::new(ProjectName::parse("project".into()), None);
+        let script_name = ScriptName::parse("down".into());
+        let script = Script::new(
+            script_name.clone(),
+            Command::parse("docker compose down".into()),
+        );
+        project.add_script(script_name.clone(), script).unwrap();
+        project
+            .set_on_leave(Some(ProjectHook::ScriptArray(vec![script_name])))
+            .unwrap();
+        let project_from_str = Project::from_str(
+            r#"
+            name = "project"
+            on_leave = [ "down" ]
+
+            [scripts]
+            down = "docker compose down"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(project, project_from_str);
+        assert!(project.get_hook_cmd().unwrap().is_none());
+        assert!(project
+            .get_on_leave_cmd()
+            .unwrap()
+            .unwrap()
+            .contains("docker compose down"));
+    }
+
+    #[test]
+    fn project_with_table_hook_and_on_leave_is_written_back() {
+        let project = Project::from_str(
+            r#"
+            name = "project"
+            hook = { steps = ["hello"] }
+            on_leave = "deactivate"
+
+            [scripts]
+            hello = "echo hello"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(Project::from_str(&project.to_string()).unwrap(), project);
+    }
+
+    #[test]
+    fn project_with_mixed_hook_is_written_back() {
+        let project = Project::from_str(
+            r#"
+            name = "project"
+            hook = ["hello", { run = "nvm use" }]
+            on_leave = { steps = ["hello", { script = "hello", background = true }] }
+
+            [scripts]
+            hello = "echo hello"
+            "#,
+        )
+        .unwrap();
+        let project_from_str = Project::from_str(&project.to_string()).unwrap();
+        assert_eq!(
+            project_from_str.get_hook_cmd().unwrap(),
+            project.get_hook_cmd().unwrap()
+        );
+        assert_eq!(
+            project_from_str.get_on_leave_cmd().unwrap(),
+            project.get_on_leave_cmd().unwrap()
+        );
+        assert_eq!(project_from_str.to_string(), project.to_string());
+    }
+
+    #[test]
+    #[should_panic]
+    fn project_fails_to_parse_invalid_on_leave_hook() {
+        Project::from_str(
+            r#"
+            name = "project"
+            on_leave = [ "down" ]
+            "#,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn project_env_parsed_successfully() {
+        let project = Project::from_str(
+            r#"
+            name = "project"
+
+            [env]
+            NODE_ENV = "development"
+            DATABASE_URL = "postgres://localhost/project"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            project.env.keys().collect::<Vec<&String>>(),
+            ["NODE_ENV", "DATABASE_URL"]
+        );
+    }
+
+    #[test]
+    fn project_fails_to_parse_invalid_env_name() {
+        let project = Project::from_str(
+            r#"
+            name = "project"
+
+            [env]
+            "NODE-ENV" = "development"
+            "#,
+        );
+        assert!(project.is_err());
+    }
+
+    #[test]
+    fn project_places_parsed_successfully() {
+        let project = Project::from_str(
+            r#"
+            name = "project"
+
+            [places]
+            web = "apps/web"
+            infra = { path = "deploy/terraform", hook = "terraform init" }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            project.get_place("web"),
+            Some(&Place::Path(PathBuf::from("apps/web")))
+        );
+        assert_eq!(
+            project.get_place("infra").unwrap().path(),
+            &PathBuf::from("deploy/terraform")
+        );
+        assert_eq!(project.get_place_hook_cmd("web").unwrap(), None);
+        assert_eq!(
+            project.resolved_commands().unwrap(),
+            vec!["places.infra (deploy/terraform): terraform init"]
+        );
+        assert_eq!(Project::from_str(&project.to_string()).unwrap(), project);
+    }
+
+    #[test]
+    fn hooks_are_approved_entry_by_entry_as_written() {
+        let project = |steps: &str| {
+            Project::from_str(&format!(
+                r#"
+                name = "project"
+                hook = {{ steps = [{}], timeout = 30 }}
+                on_leave = "echo bye"
+
+                [scripts]
+                install = "npm ci"
+                "#,
+                steps
+            ))
+            .unwrap()
+        };
+        let before = project(
+            r#""install", { run = "nvm use", background = true, when = { branch = "main" } }"#,
+        );
+        assert_eq!(
+            before.resolved_commands().unwrap(),
+            vec![
+                "hook: install: npm ci",
+                "hook: nvm use & (when branch = \"main\")",
+                "hook: timeout = 30",
+                "on_leave: echo bye",
+            ]
+        );
+
+        let after = project(
+            r#""install", { run = "nvm use", background = true, when = { branch = "main" } }, { run = "make" }"#,
+        );
+        assert_eq!(
+            crate::trust::diff(
+                &before.resolved_commands().unwrap(),
+                &after.resolved_commands().unwrap()
+            ),
+            "+ hook: make".green().to_string()
+        );
+    }
+
+    #[test]
+    fn project_fails_to_parse_invalid_places() {
+        let cases = [
+            r#"places = { "web:app" = "apps/web" }"#,
+            r#"places = { web = "/srv/web" }"#,
+            r#"places = { web = { path = "apps/web", hook = ["missing"] } }"#,
+        ];
+        for case in cases {
+            let project = Project::from_str(&format!("name = \"project\"\n{}", case));
+            assert!(project.is_err(), "{}", case);
+        }
+    }
+
     #[test]
     #[should_panic]
     fn project_fails_to_add_invalid_hook_successfully() {
@@ -327,6 +717,27 @@ mod test {
             .unwrap();
     }
 
+    #[test]
+    fn added_entries_are_scripts_unless_inline() {
+        let project = Project::from_str(
+            r#"
+            name = "project"
+            hook = ["install"]
+
+            [scripts]
+            install = "npm ci"
+            "#,
+        )
+        .unwrap();
+        let add = |input: &str, inline: bool| {
+            let hook = project.hook().clone().unwrap();
+            Some(hook.add_entry(HookEntry::from_input(input, inline)))
+        };
+        assert!(project.validate_hook(&add("instal", false)).is_err());
+        assert!(project.validate_hook(&add("instal", true)).is_ok());
+        assert!(project.validate_hook(&add("install", false)).is_ok());
+    }
+
     #[test]
     #[should_panic]
     fn project_fails_to_parse_invalid_hook() {
@@ -431,6 +842,7 @@ world = "echo \"world\""
             ("cantthisbegoo>".to_string(), "cannot contain gt sign"),
             ("cantcauseof{".to_string(), "cannot contain bracket"),
             ("cantcauseof}".to_string(), "cannot contain bracket"),
+            ("api:web".to_string(), "cannot contain colon"),
         ];
         for (case, msg) in cases {
             let result = panic::catch_unwind(|| ProjectName::parse(case.clone()));
@@ -443,10 +855,50 @@ world = "echo \"world\""
         }
     }
 
+    #[test]
+    fn suffixes_cut_project_names_short() {
+        let name = |s: &str| ProjectName::parse(s.into());
+        assert_eq!(name("api").with_suffix("-2"), name("api-2"));
+        assert_eq!(
+            name("a-really-long-name-x").with_suffix("-12"),
+            name("a-really-long-nam-12")
+        );
+        assert_eq!(name("ação-çççççç").with_suffix("-2"), name("ação-ççççç-2"));
+    }
+
+    #[test]
+    fn project_names_are_sanitized() {
+        let sanitize = |s: &str| ProjectName::sanitize(s).map(|name| name.to_string());
+        assert_eq!(sanitize("krabby"), Some("krabby".to_string()));
+        assert_eq!(
+            sanitize("my project (old)"),
+            Some("my-project--old".to_string())
+        );
+        assert_eq!(sanitize("api:v2"), Some("api-v2".to_string()));
+        assert_eq!(
+            sanitize("a-really-long-project-name"),
+            Some("a-really-long-projec".to_string())
+        );
+        assert_eq!(
+            sanitize("ação-çççççççççççç"),
+            Some("ação-çççççç".to_string())
+        );
+        assert_eq!(sanitize("()"), None);
+        for case in [
+            "my project (old)",
+            "a-really-long-project-name",
+            "ação-çççççççççççç",
+        ] {
+            let name = ProjectName::sanitize(case).unwrap();
+            ProjectName::parse(name.to_string());
+        }
+    }
+
     use rand::distributions::Alphanumeric;
     use rand::{thread_rng, Rng};
     use std::fs;
 
+    #[allow(dead_code)]
     fn create_random_project_file() -> (ProjectName, PathBuf) {
         let mut path = std::env::temp_dir();
         let rand_string: String = thread_rng()
@@ -0,0 +1,197 @@
+use crate::{database::Database, project::ProjectName};
+use anyhow::{anyhow, Error};
+use owo_colors::OwoColorize;
+use std::path::{Path, PathBuf};
+
+/// Finds the directories under `root`, down to `depth` levels below it, that look like projects:
+/// git repositories and directories with a krabby.toml. Projects are not searched for projects
+/// of their own, and hidden directories are left out.
+pub fn find_projects(root: &Path, depth: usize) -> Vec<PathBuf> {
+    let mut projects = Vec::new();
+    if is_project(root) {
+        projects.push(root.to_path_buf());
+        return projects;
+    }
+    let mut pending = vec![(root.to_path_buf(), 0)];
+    while let Some((dir, level)) = pending.pop() {
+        if level >= depth {
+            continue;
+        }
+        let Ok(children) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        let children = children
+            .filter_map(|child| child.ok())
+            .filter(|child| !child.file_name().to_string_lossy().starts_with('.'))
+            .map(|child| child.path())
+            // Symlinks are left out, so nothing is found twice or in a loop.
+            .filter(|child| child.is_dir() && !child.is_symlink());
+        for child in children {
+            match is_project(&child) {
+                true => projects.push(child),
+                false => pending.push((child, level + 1)),
+            }
+        }
+    }
+    projects.sort();
+    projects
+}
+
+fn is_project(dir: &Path) -> bool {
+    dir.join(".git").exists() || dir.join("krabby.toml").is_file()
+}
+
+/// What registering a bunch of directories at once would do.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Proposal {
+    /// The projects to register, named after their directory.
+    pub candidates: Vec<(ProjectName, PathBuf)>,
+    /// Directories registered already, under any name.
+    pub registered: Vec<PathBuf>,
+    /// Directories whose name is taken, by a registered project or by an earlier candidate.
+    pub collisions: Vec<(ProjectName, PathBuf)>,
+    /// Directories with nothing in their name krabby could use.
+    pub unnamed: Vec<PathBuf>,
+}
+
+/// Proposes a name for each directory in `paths` that is not registered in `database` yet.
+pub fn propose(database: &Database, paths: Vec<PathBuf>) -> Proposal {
+    let mut proposal = Proposal::default();
+    for path in paths {
+        if database.projects.values().any(|entry| entry.path == path) {
+            proposal.registered.push(path);
+            continue;
+        }
+        let name = path
+            .file_name()
+            .and_then(|name| ProjectName::sanitize(&name.to_string_lossy()));
+        let Some(name) = name else {
+            proposal.unnamed.push(path);
+            continue;
+        };
+        let taken = database.canonical_name(&name).is_some()
+            || proposal
+                .candidates
+                .iter()
+                .any(|(candidate, _)| *candidate == name);
+        match taken {
+            true => proposal.collisions.push((name, path)),
+            false => proposal.candidates.push((name, path)),
+        }
+    }
+    proposal
+}
+
+/// Reads which of `count` candidates the user picked, numbered from 1:
+/// `all` (or nothing at all), `none`, or numbers and ranges like `1 3-5`.
+pub fn parse_selection(selection: &str, count: usize) -> Result<Vec<usize>, Error> {
+    match selection.trim() {
+        "" | "all" => return Ok((0..count).collect()),
+        "none" => return Ok(Vec::new()),
+        _ => (),
+    }
+    let invalid = |part: &str| anyhow!("{} is not one of the options.", part.bold());
+    let mut picked = Vec::new();
+    for part in selection.split([' ', ',']).filter(|part| !part.is_empty()) {
+        let (first, last) = part.split_once('-').unwrap_or((part, part));
+        let (Ok(first), Ok(last)) = (first.parse::<usize>(), last.parse::<usize>()) else {
+            return Err(invalid(part));
+        };
+        if first == 0 || last > count || first > last {
+            return Err(invalid(part));
+        }
+        for index in first - 1..last {
+            if !picked.contains(&index) {
+                picked.push(index);
+            }
+        }
+    }
+    Ok(picked)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn scan_finds_repositories_and_project_files() {
+        let root = std::env::temp_dir().join(format!("krabby-scan-{}", std::process::id()));
+        for dir in [
+            "code/api/.git",
+            "code/api/vendor/lib/.git",
+            "code/web",
+            "code/tools/deep/down/.git",
+            "notes",
+            ".hidden/.git",
+        ] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        fs::write(root.join("code/web/krabby.toml"), "name = \"web\"\n").unwrap();
+
+        assert_eq!(
+            find_projects(&root, 3),
+            vec![root.join("code/api"), root.join("code/web")]
+        );
+        assert_eq!(
+            find_projects(&root, 4),
+            vec![
+                root.join("code/api"),
+                root.join("code/tools/deep/down"),
+                root.join("code/web")
+            ]
+        );
+        assert!(find_projects(&root, 1).is_empty());
+        assert_eq!(
+            find_projects(&root.join("code/api"), 3),
+            vec![root.join("code/api")]
+        );
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn proposals_skip_registered_paths_and_report_collisions() {
+        let database = Database::from_string(
+            r#"
+            [projects]
+            api = "/code/api"
+            web = "/code/web"
+            "#,
+        );
+        let paths = [
+            "/code/api",
+            "/old/web",
+            "/code/cli tool",
+            "/work/cli tool",
+            "/code/()",
+        ];
+        let proposal = propose(&database, paths.iter().map(PathBuf::from).collect());
+        let name = |name: &str| ProjectName::parse(name.into());
+        assert_eq!(
+            proposal,
+            Proposal {
+                candidates: vec![(name("cli-tool"), PathBuf::from("/code/cli tool"))],
+                registered: vec![PathBuf::from("/code/api")],
+                collisions: vec![
+                    (name("web"), PathBuf::from("/old/web")),
+                    (name("cli-tool"), PathBuf::from("/work/cli tool"))
+                ],
+                unnamed: vec![PathBuf::from("/code/()")],
+            }
+        );
+    }
+
+    #[test]
+    fn selections_take_numbers_and_ranges() {
+        assert_eq!(parse_selection("", 3).unwrap(), vec![0, 1, 2]);
+        assert_eq!(parse_selection("all", 3).unwrap(), vec![0, 1, 2]);
+        assert!(parse_selection("none", 3).unwrap().is_empty());
+        assert_eq!(parse_selection("3 1", 3).unwrap(), vec![2, 0]);
+        assert_eq!(parse_selection("1-2,2", 3).unwrap(), vec![0, 1]);
+        assert!(parse_selection("0", 3).is_err());
+        assert!(parse_selection("4", 3).is_err());
+        assert!(parse_selection("3-1", 3).is_err());
+        assert!(parse_selection("api", 3).is_err());
+    }
+}
